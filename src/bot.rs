@@ -1,71 +1,195 @@
-use std::any;
 use std::fmt::Debug;
-use std::io;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+#[cfg(feature = "async_api")]
+use futures_util::{stream, Stream, StreamExt};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
-use thiserror::Error;
+#[cfg(feature = "async_api")]
+use tokio_util::io::ReaderStream;
 
+use crate::error::{WeComError, WeComResult, API_MEDIA_TOO_LARGE, API_RATE_LIMITED};
 use crate::media::MediaType;
-use crate::message::Message;
-use crate::response::UploadResp;
+use crate::message::{Message, MARKDOWN_MAX_BYTES, TEXT_MAX_BYTES};
+use crate::response::{ApiError, SendResp, UploadResp};
 
-#[derive(Debug, Error)]
-#[non_exhaustive]
-pub enum WeComError {
-    #[error("wecom bot key not set")]
-    KeyNotFound,
-    #[error("network failed: {source}")]
-    Network {
-        #[from]
-        source: reqwest::Error,
-    },
-    #[error("wecom bot server error: {status}")]
-    Http { status: reqwest::StatusCode },
-    #[error("could not parse {typename} data from JSON: {source}")]
-    DataType {
-        #[source]
-        source: serde_json::Error,
-        typename: &'static str,
-    },
-    #[error("failed to read image file: {source}")]
-    ImageRead {
-        #[from]
-        source: io::Error,
-    },
-    #[error("failed to read upload file: {source}")]
-    FileRead { source: io::Error },
-    #[error("unknown upload media type: {0}")]
-    MediaType(String),
+/// Details of a sent message request, returned by `send_with_meta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendMeta {
+    /// The exact size of the JSON payload posted, in bytes.
+    pub payload_bytes: usize,
+    /// The HTTP status of the response.
+    pub status: StatusCode,
 }
 
-impl WeComError {
-    pub(crate) fn network(source: reqwest::Error) -> Self {
-        WeComError::Network { source }
+/// Turns a `SendResp` with a non-zero `errcode` into `WeComError::Api`.
+fn check_send_resp(resp: SendResp) -> WeComResult<()> {
+    if resp.is_ok() {
+        Ok(())
+    } else {
+        Err(WeComError::Api {
+            err_code: resp.err_code,
+            err_msg: resp.err_msg,
+        })
     }
+}
 
-    pub(crate) fn data_type<T>(source: serde_json::Error) -> Self {
-        WeComError::DataType {
-            source,
-            typename: any::type_name::<T>(),
+/// Emits a debug `tracing` event of the sent message, with its type, payload
+/// size and the resulting `errcode`, the webhook key is never logged.
+#[cfg(feature = "tracing")]
+fn trace_sent(msg: &Message<'_>, body: &WeComResult<(Vec<u8>, SendMeta)>) {
+    match body {
+        Ok((body, meta)) => {
+            let errcode = serde_json::from_slice::<ApiError>(body)
+                .ok()
+                .map(|e| e.err_code);
+            tracing::debug!(
+                msgtype = msg.msg_type(),
+                payload_bytes = meta.payload_bytes,
+                errcode,
+                "wecom bot message sent"
+            );
         }
+        Err(e) => tracing::debug!(
+            msgtype = msg.msg_type(),
+            error = %e,
+            "wecom bot message failed"
+        ),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+fn trace_sent(_msg: &Message<'_>, _body: &WeComResult<(Vec<u8>, SendMeta)>) {}
+
+/// Emits a debug `tracing` event of the exact request payload and the raw
+/// response body if `debug_payloads` is enabled, with the key of the url
+/// redacted as in the `Debug` output of the bot.
+#[cfg(feature = "tracing")]
+fn trace_payloads(enabled: bool, url: &str, key_reveal: usize, payload: &str, body: &[u8]) {
+    if enabled {
+        tracing::debug!(
+            url = %redact_key(url, key_reveal),
+            payload = %payload,
+            body = %String::from_utf8_lossy(body),
+            "wecom bot payloads"
+        );
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+fn trace_payloads(_enabled: bool, _url: &str, _key_reveal: usize, _payload: &str, _body: &[u8]) {}
+
+/// Emits a debug `tracing` event of the uploaded file, with its media type,
+/// size and the resulting `errcode`.
+#[cfg(feature = "tracing")]
+fn trace_uploaded(media_type: MediaType, path: &Path, uploaded: &WeComResult<UploadResp>) {
+    let file_bytes = std::fs::metadata(path).map(|m| m.len()).ok();
+    match uploaded {
+        Ok(resp) => tracing::debug!(
+            media_type = media_type.as_str(),
+            file_bytes,
+            errcode = resp.err_code,
+            "wecom bot file uploaded"
+        ),
+        Err(e) => tracing::debug!(
+            media_type = media_type.as_str(),
+            file_bytes,
+            error = %e,
+            "wecom bot file upload failed"
+        ),
     }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+fn trace_uploaded(_media_type: MediaType, _path: &Path, _uploaded: &WeComResult<UploadResp>) {}
+
+/// Returns the `media_id` of a successful upload, or the error of
+/// `check_upload` if the upload is rejected.
+fn check_upload_resp(resp: UploadResp) -> WeComResult<String> {
+    check_upload(resp).map(|resp| resp.media_id)
+}
 
-    pub(crate) fn image(source: io::Error) -> Self {
-        WeComError::ImageRead { source }
+/// Returns the upload response if it succeeded, `WeComError::MediaTooLarge`
+/// if the file exceeds the size limit, or `WeComError::Api` for the other
+/// rejections.
+fn check_upload(resp: UploadResp) -> WeComResult<UploadResp> {
+    match resp.err_code {
+        0 => Ok(resp),
+        API_MEDIA_TOO_LARGE => Err(WeComError::MediaTooLarge {
+            err_msg: resp.err_msg,
+        }),
+        err_code => Err(WeComError::Api {
+            err_code,
+            err_msg: resp.err_msg,
+        }),
     }
+}
+
+/// Parses the response body into `T`, falling back to the wecom error body
+/// when `T` does not match so that API errors are reported as `Api`.
+fn parse_body<T>(body: &[u8]) -> WeComResult<T>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_slice::<T>(body).map_err(|e| match serde_json::from_slice::<ApiError>(body) {
+        Ok(api) if api.err_code != 0 => WeComError::Api {
+            err_code: api.err_code,
+            err_msg: api.err_msg,
+        },
+        _ => WeComError::data_type::<T>(e, body),
+    })
+}
 
-    pub(crate) fn load_file(source: io::Error) -> Self {
-        WeComError::FileRead { source }
+/// Returns `WeComError::RateLimited` if the response body reports the
+/// frequency limit was exceeded, with the wait of the `Retry-After` header.
+/// The request is retried on it before the error is returned.
+fn check_rate_limited(headers: &HeaderMap, body: &[u8]) -> WeComResult<()> {
+    match serde_json::from_slice::<ApiError>(body) {
+        Ok(api) if api.err_code == API_RATE_LIMITED => Err(WeComError::RateLimited {
+            retry_after: headers
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_secs),
+        }),
+        _ => Ok(()),
     }
 }
 
-type WeComResult<T> = Result<T, WeComError>;
+/// The `errcode` of the wecom bot API for an invalid webhook key.
+const API_INVALID_WEBHOOK: i64 = 93000;
+
+/// The text message posted by `check_key`.
+const CHECK_KEY_CONTENT: &str = "wecom_bot: webhook key check";
+
+fn key_accepted(sent: WeComResult<SendResp>) -> WeComResult<bool> {
+    match sent {
+        Ok(resp) => Ok(resp.err_code != API_INVALID_WEBHOOK),
+        Err(WeComError::RateLimited { .. }) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
 
+#[derive(Clone)]
 pub struct WeComBot {
     url: String,
     upload_base_url: String,
+    upload_field_name: String,
+    max_text_bytes: usize,
+    max_markdown_bytes: usize,
+    max_retries: usize,
+    retry_backoff: Duration,
+    headers: HeaderMap,
+    dry_run: Option<LastSent>,
+    debug_payloads: bool,
+    key_reveal: usize,
+    request_hook: Option<RequestHook<reqwest::blocking::RequestBuilder>>,
 
     client: reqwest::blocking::Client,
 }
@@ -84,18 +208,213 @@ impl WeComBot {
         WeComBotBuilder::new()
     }
 
+    /// Returns a `WeComBot` with the default configuration that sends through
+    /// a clone of the shared `client`, so that many bots share one connection
+    /// pool instead of creating their own.
+    pub fn with_shared_client<K>(
+        key: K,
+        client: &Arc<reqwest::blocking::Client>,
+    ) -> WeComResult<WeComBot>
+    where
+        K: Into<String>,
+    {
+        WeComBotBuilder::new()
+            .key(key)
+            .shared_client(client)
+            .build()
+    }
+
+    /// Returns a `WeComBot` with the key read from the `WECOM_BOT_KEY` environment
+    /// variable, and the base url from `WECOM_BOT_BASE_URL` if it is set.
+    ///
+    /// Returns `WeComError::KeyNotFound` if `WECOM_BOT_KEY` is missing or empty.
+    pub fn from_env() -> WeComResult<WeComBot> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    fn from_env_with<F>(lookup: F) -> WeComResult<WeComBot>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let (key, base_url) = read_env(lookup)?;
+        let mut builder = WeComBotBuilder::new().key(key);
+        if let Some(base_url) = base_url {
+            builder = builder.base_url(base_url);
+        }
+        builder.build()
+    }
+
+    /// Returns the underlying HTTP client, e.g. to fetch an `Image` from url.
+    pub fn client(&self) -> &reqwest::blocking::Client {
+        &self.client
+    }
+
+    /// Returns the url messages are sent to.
+    ///
+    /// The url contains the secret webhook key, take care when logging it.
+    pub fn send_url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the url files are uploaded to, without the `type` parameter.
+    ///
+    /// The url contains the secret webhook key, take care when logging it.
+    pub fn upload_url_base(&self) -> &str {
+        &self.upload_base_url
+    }
+
+    /// Returns the JSON payload of the last message sent in dry run mode, or
+    /// `None` if nothing was sent or dry run is disabled.
+    pub fn last_sent(&self) -> Option<String> {
+        self.dry_run.as_ref()?.lock().unwrap().clone()
+    }
+
     /// Constructs the wecom bot `Message` and sends it to wecom bot API.
     pub fn send<T>(&self, msg: Message<'_>) -> WeComResult<T>
     where
         T: DeserializeOwned,
     {
-        let resp = self.client.post(&self.url).json(&msg).send()?;
-        let status = resp.status();
-        if status.is_server_error() {
-            return Err(WeComError::Http { status });
+        let (body, _) = self.send_body(msg)?;
+        parse_body::<T>(&body)
+    }
+
+    /// Same as `send` but returns the default `SendResp`, without the need of
+    /// a type annotation.
+    ///
+    /// ```
+    /// # use wecom_bot::{Message, WeComBot, WeComError};
+    /// # fn main() -> Result<(), WeComError> {
+    /// let bot = WeComBot::builder()
+    ///     .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+    ///     .dry_run(true)
+    ///     .build()?;
+    /// let rsp = bot.send_text_msg(Message::text("hello world!"))?;
+    /// assert!(rsp.is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn send_text_msg(&self, msg: Message<'_>) -> WeComResult<SendResp> {
+        self.send(msg)
+    }
+
+    /// Same as `send` but also returns the `SendMeta` of the request, e.g. to
+    /// account the payload size against the quota of wecom bot API.
+    pub fn send_with_meta<T>(&self, msg: Message<'_>) -> WeComResult<(T, SendMeta)>
+    where
+        T: DeserializeOwned,
+    {
+        let (body, meta) = self.send_body(msg)?;
+        Ok((parse_body::<T>(&body)?, meta))
+    }
+
+    /// Same as `send` but also returns the raw response body, e.g. to log
+    /// exactly what the wecom bot API returned.
+    pub fn send_raw(&self, msg: Message<'_>) -> WeComResult<(String, SendResp)> {
+        let (body, _) = self.send_body(msg)?;
+        let resp = parse_body::<SendResp>(&body)?;
+        Ok((String::from_utf8_lossy(&body).into_owned(), resp))
+    }
+
+    /// Sends the message and succeeds only if the wecom bot API accepted it,
+    /// a non-zero `errcode` is returned as `WeComError::Api`.
+    pub fn notify(&self, msg: Message<'_>) -> WeComResult<()> {
+        check_send_resp(self.send::<SendResp>(msg)?)
+    }
+
+    /// Posts the message and returns the response body.
+    fn send_body(&self, msg: Message<'_>) -> WeComResult<(Vec<u8>, SendMeta)> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("wecom_bot.send", msgtype = msg.msg_type()).entered();
+
+        let body = self.post_message(&msg);
+        trace_sent(&msg, &body);
+        body
+    }
+
+    fn post_message(&self, msg: &Message<'_>) -> WeComResult<(Vec<u8>, SendMeta)> {
+        msg.check_content_limits(self.max_text_bytes, self.max_markdown_bytes)?;
+        let payload = msg.to_json().expect("message is always serializable");
+        if let Some(last_sent) = &self.dry_run {
+            return Ok(dry_run_send(last_sent, payload));
+        }
+
+        let payload_bytes = payload.len();
+        let mut attempt = 0;
+        loop {
+            let sent = self
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.clone())
+                .send();
+            let resp = match sent.map_err(WeComError::network) {
+                Ok(resp) => resp,
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    std::thread::sleep(self.retry_backoff);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.bytes().unwrap_or_default();
+                trace_payloads(
+                    self.debug_payloads,
+                    &self.url,
+                    self.key_reveal,
+                    &payload,
+                    &body,
+                );
+                return Err(WeComError::http(status, &body));
+            }
+
+            let meta = SendMeta {
+                payload_bytes,
+                status,
+            };
+            let headers = resp.headers().clone();
+            let body = resp.bytes()?.to_vec();
+            trace_payloads(
+                self.debug_payloads,
+                &self.url,
+                self.key_reveal,
+                &payload,
+                &body,
+            );
+            match check_rate_limited(&headers, &body) {
+                Ok(()) => return Ok((body, meta)),
+                Err(WeComError::RateLimited { retry_after }) if attempt < self.max_retries => {
+                    attempt += 1;
+                    std::thread::sleep(retry_after.unwrap_or(self.retry_backoff));
+                }
+                Err(e) => return Err(e),
+            }
         }
+    }
+
+    /// Returns whether the webhook key is accepted by the wecom bot API, or
+    /// `false` if it is rejected as an invalid webhook with `errcode` 93000.
+    /// Other API errors, including the rate limit, mean the key is valid.
+    ///
+    /// wecom has no request to only validate a key, so this posts a short
+    /// text message which is visible in the group if the key is valid.
+    pub fn check_key(&self) -> WeComResult<bool> {
+        key_accepted(self.send(Message::text(CHECK_KEY_CONTENT)))
+    }
+
+    /// Sends the messages one by one and collects the result of each, in the
+    /// same order. A failed message does not stop the rest from being sent.
+    pub fn send_all<'m, I>(&self, msgs: I) -> Vec<WeComResult<SendResp>>
+    where
+        I: IntoIterator<Item = Message<'m>>,
+    {
+        msgs.into_iter().map(|msg| self.send(msg)).collect()
+    }
 
-        serde_json::from_reader::<_, T>(resp).map_err(WeComError::data_type::<T>)
+    /// Sends `content` as text messages in order, split into chunks of no
+    /// more than 2048 bytes by `Message::text_chunks`.
+    pub fn send_chunked(&self, content: &str) -> Vec<WeComResult<SendResp>> {
+        self.send_all(Message::text_chunks(content))
     }
 
     /// Constructs the file uploader to upload local file to the wecom bot server.
@@ -103,51 +422,394 @@ impl WeComBot {
     where
         P: AsRef<Path>,
     {
-        let file = reqwest::blocking::multipart::Form::new()
-            .file("filename", path)
-            .map_err(WeComError::load_file)?;
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("wecom_bot.upload", media_type = media_type.as_str()).entered();
+
+        let uploaded = self.upload_file(media_type, path.as_ref());
+        trace_uploaded(media_type, path.as_ref(), &uploaded);
+        uploaded
+    }
+
+    /// Same as `upload` but succeeds only if the wecom bot API accepted the
+    /// file, a non-zero `errcode` is returned as `WeComError::Api`, or as
+    /// `WeComError::MediaTooLarge` if the file exceeds the size limit.
+    pub fn upload_checked<P>(&self, media_type: MediaType, path: P) -> WeComResult<UploadResp>
+    where
+        P: AsRef<Path>,
+    {
+        check_upload(self.upload(media_type, path)?)
+    }
+
+    /// Same as `upload` but calls `progress` with the bytes sent so far and
+    /// the total bytes of the file as the upload goes, e.g. to draw a
+    /// progress bar of a large file.
+    ///
+    /// The progress starts over from zero if the upload is retried.
+    pub fn upload_with_progress<P, F>(
+        &self,
+        media_type: MediaType,
+        path: P,
+        progress: F,
+    ) -> WeComResult<UploadResp>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("wecom_bot.upload", media_type = media_type.as_str()).entered();
+
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let progress = Arc::new(Mutex::new(progress));
+        let uploaded =
+            self.upload_form(media_type, || {
+                let file = std::fs::File::open(path).map_err(WeComError::load_file)?;
+                let total = file.metadata().map_err(WeComError::load_file)?.len();
+                let reader = ProgressReader {
+                    inner: file,
+                    sent: 0,
+                    total,
+                    progress: Arc::clone(&progress),
+                };
+                let part = reqwest::blocking::multipart::Part::reader_with_length(reader, total)
+                    .file_name(filename.clone());
+                Ok(reqwest::blocking::multipart::Form::new()
+                    .part(self.upload_field_name.clone(), part))
+            });
+        trace_uploaded(media_type, path, &uploaded);
+        uploaded
+    }
+
+    fn upload_file(&self, media_type: MediaType, path: &Path) -> WeComResult<UploadResp> {
+        self.upload_form(media_type, || {
+            reqwest::blocking::multipart::Form::new()
+                .file(self.upload_field_name.clone(), path)
+                .map_err(WeComError::load_file)
+        })
+    }
+
+    fn upload_form<F>(&self, media_type: MediaType, form: F) -> WeComResult<UploadResp>
+    where
+        F: Fn() -> WeComResult<reqwest::blocking::multipart::Form>,
+    {
+        if self.dry_run.is_some() {
+            return Ok(dry_run_upload(media_type));
+        }
 
         let upload_url = media_type.format_upload_url(&self.upload_base_url);
-        let resp = self.client.post(upload_url).multipart(file).send()?;
+
+        let mut attempt = 0;
+        let resp = loop {
+            // the form is rebuilt for each attempt to read the file from the start
+            let sent = self.post(&upload_url).multipart(form()?).send();
+            match sent.map_err(WeComError::network) {
+                Ok(resp) => break resp,
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    std::thread::sleep(self.retry_backoff);
+                }
+                Err(e) => return Err(e),
+            }
+        };
         let status = resp.status();
-        if status.is_server_error() {
-            return Err(WeComError::Http { status });
+        if !status.is_success() {
+            return Err(WeComError::http(status, &resp.bytes().unwrap_or_default()));
         }
 
-        let ret: UploadResp = resp.json()?;
-        Ok(ret)
+        let body = resp.bytes()?;
+        serde_json::from_slice::<UploadResp>(&body)
+            .map_err(|e| WeComError::data_type::<UploadResp>(e, &body))
+    }
+
+    /// Uploads the local file then sends it as a file `Message`.
+    ///
+    /// Returns the error of `upload_checked` without sending if the upload is
+    /// rejected.
+    pub fn upload_and_send_file<P>(&self, path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        let uploaded = self.upload(MediaType::File, path)?;
+        let media_id = check_upload_resp(uploaded)?;
+        self.send(Message::file(media_id))
+    }
+
+    /// Uploads the local AMR file then sends it as a voice `Message`.
+    ///
+    /// Returns the error of `upload_checked` without sending if the upload is
+    /// rejected.
+    pub fn upload_and_send_voice<P>(&self, path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        let uploaded = self.upload(MediaType::Voice, path)?;
+        let media_id = check_upload_resp(uploaded)?;
+        self.send(Message::voice(media_id))
+    }
+
+    /// Always returns `WeComError::NotSendable`, the wecom bot API accepts
+    /// video uploads but has no video message to send them with.
+    ///
+    /// Use `upload` with `MediaType::Video`, or send the video as a file.
+    pub fn upload_and_send_video<P>(&self, _path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        Err(WeComError::NotSendable(MediaType::Video))
+    }
+}
+
+impl WeComBot {
+    fn post(&self, url: &str) -> reqwest::blocking::RequestBuilder {
+        let builder = self.client.post(url).headers(self.headers.clone());
+        match &self.request_hook {
+            Some(hook) => (hook.0)(builder),
+            None => builder,
+        }
     }
 }
 
 impl Debug for WeComBot {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("WeComBot").field("url", &self.url).finish()
+        f.debug_struct("WeComBot")
+            .field("url", &redact_key(&self.url, self.key_reveal))
+            .finish()
+    }
+}
+
+/// The default number of leading and trailing key chars kept by `redact_key`.
+const DEFAULT_KEY_REVEAL: usize = 4;
+
+/// Masks the value of the `key` query parameter in `url`, so that the webhook
+/// key does not leak into logs while the scheme and host stay visible.
+///
+/// Only the leading and trailing `reveal` chars of the key are kept, short
+/// keys are masked entirely.
+fn redact_key(url: &str, reveal: usize) -> String {
+    let Some((prefix, key)) = url.split_once("key=") else {
+        return url.to_string();
+    };
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= reveal.saturating_mul(2) {
+        return format!("{}key=****", prefix);
     }
+    let head: String = chars[..reveal].iter().collect();
+    let tail: String = chars[chars.len() - reveal..].iter().collect();
+    format!("{}key={}****{}", prefix, head, tail)
 }
 
-const WECOM_SEND_URL: &str = "https://qyapi.weixin.qq.com/cgi-bin/webhook/send";
-const WECOM_UPLOAD_URL: &str = "https://qyapi.weixin.qq.com/cgi-bin/webhook/upload_media";
+const WECOM_BASE_URL: &str = "https://qyapi.weixin.qq.com/cgi-bin/webhook";
 
 macro_rules! format_wecom_url {
-    ($key:expr) => {
+    ($base:expr, $key:expr, $allow_any_key:expr) => {
         match $key {
             None => return Err(WeComError::KeyNotFound),
             Some(k) => {
                 if k.trim().len() == 0 {
                     return Err(WeComError::KeyNotFound);
                 }
+                if !$allow_any_key && !is_webhook_key(&k) {
+                    return Err(WeComError::InvalidKey);
+                }
+                let base = $base
+                    .as_deref()
+                    .unwrap_or(WECOM_BASE_URL)
+                    .trim_end_matches('/');
+                // keys issued by a custom gateway may contain reserved chars
+                let k: String = url::form_urlencoded::byte_serialize(k.as_bytes()).collect();
                 (
-                    format!("{}?key={}", WECOM_SEND_URL, k),
-                    format!("{}?key={}", WECOM_UPLOAD_URL, k),
+                    format!("{}/send?key={}", base, k),
+                    format!("{}/upload_media?key={}", base, k),
                 )
             }
         }
     };
 }
 
-#[derive(Debug, Default)]
+/// Returns whether `key` has the shape of a wecom webhook key, which is a
+/// 36 characters UUID with hyphens, e.g. `693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa`.
+fn is_webhook_key(key: &str) -> bool {
+    key.len() == 36
+        && key.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_alphanumeric(),
+        })
+}
+
+/// Extracts the `key` query parameter from a full webhook url.
+fn webhook_key(url: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    let query = query.split('#').next().unwrap_or_default();
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(name, _)| name == "key")
+        .map(|(_, key)| key.into_owned())
+}
+
+/// Builds the default HTTP client from the `$opts` builder configuration.
+macro_rules! build_default_client {
+    ($client_builder:expr, $opts:expr) => {{
+        let mut builder = $client_builder
+            .timeout($opts.timeout.unwrap_or(DEFAULT_TIMEOUT))
+            .user_agent($opts.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+        if let Some(timeout) = $opts.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = $opts.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = $opts.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(proxy) = $opts.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        {
+            builder = builder.danger_accept_invalid_certs($opts.danger_accept_invalid_certs);
+        }
+        #[cfg(feature = "gzip")]
+        if let Some(gzip) = $opts.gzip {
+            builder = builder.gzip(gzip);
+        }
+        builder.build()?
+    }};
+}
+
+/// Reads the uploaded file and reports the bytes read so far to the callback
+/// of `upload_with_progress`.
+struct ProgressReader<R, F> {
+    inner: R,
+    sent: u64,
+    total: u64,
+    progress: Arc<Mutex<F>>,
+}
+
+impl<R, F> std::io::Read for ProgressReader<R, F>
+where
+    R: std::io::Read,
+    F: FnMut(u64, u64),
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.sent += n as u64;
+            (self.progress.lock().unwrap())(self.sent, self.total);
+        }
+        Ok(n)
+    }
+}
+
+/// The payload of the last message sent by a bot in dry run mode, shared
+/// between its clones.
+type LastSent = Arc<Mutex<Option<String>>>;
+
+/// The closure set by `request_hook`, applied to every outgoing request and
+/// shared between the clones of a bot.
+struct RequestHook<B>(Arc<dyn Fn(B) -> B + Send + Sync>);
+
+impl<B> Clone for RequestHook<B> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<B> Debug for RequestHook<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestHook")
+    }
+}
+
+const DRY_RUN_SEND_RESP: &str = r#"{"errcode":0,"errmsg":"ok"}"#;
+
+/// Records the message payload as sent and returns a successful response.
+fn dry_run_send(last_sent: &LastSent, payload: String) -> (Vec<u8>, SendMeta) {
+    let meta = SendMeta {
+        payload_bytes: payload.len(),
+        status: StatusCode::OK,
+    };
+    *last_sent.lock().unwrap() = Some(payload);
+    (DRY_RUN_SEND_RESP.as_bytes().to_vec(), meta)
+}
+
+fn dry_run_upload(media_type: MediaType) -> UploadResp {
+    UploadResp {
+        media_type: media_type.to_string(),
+        media_id: String::from("dry-run"),
+        ..Default::default()
+    }
+}
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `User-Agent` of the default client.
+const DEFAULT_USER_AGENT: &str = concat!("wecom_bot/", env!("CARGO_PKG_VERSION"));
+
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The multipart field name of the uploaded file expected by wecom.
+const DEFAULT_UPLOAD_FIELD_NAME: &str = "filename";
+
+/// The environment variable `from_env` reads the webhook key from.
+const ENV_BOT_KEY: &str = "WECOM_BOT_KEY";
+
+/// The environment variable `from_env` reads the optional base url from.
+const ENV_BOT_BASE_URL: &str = "WECOM_BOT_BASE_URL";
+
+/// Returns the webhook key and the optional base url looked up from the
+/// environment variables.
+fn read_env<F>(lookup: F) -> WeComResult<(String, Option<String>)>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let key = lookup(ENV_BOT_KEY)
+        .filter(|key| !key.trim().is_empty())
+        .ok_or(WeComError::KeyNotFound)?;
+    let base_url = lookup(ENV_BOT_BASE_URL).filter(|url| !url.trim().is_empty());
+    Ok((key, base_url))
+}
+
+/// Returns whether the request failed on a transient network error, such as
+/// a refused or reset connection or a timeout, and is safe to be sent again
+/// from scratch. HTTP, API and parse errors are never retried.
+fn is_retryable(err: &WeComError) -> bool {
+    match err {
+        WeComError::Network { source } => {
+            source.is_connect() || source.is_timeout() || source.is_request()
+        }
+        _ => false,
+    }
+}
+
+#[must_use = "builders do nothing unless `build` is called"]
+#[derive(Debug, Default, Clone)]
 pub struct WeComBotBuilder {
     key: Option<String>,
+    allow_any_key: bool,
+    base_url: Option<String>,
+    upload_field_name: Option<String>,
+    max_text_bytes: Option<usize>,
+    max_markdown_bytes: Option<usize>,
+    max_retries: usize,
+    retry_backoff: Option<Duration>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    danger_accept_invalid_certs: bool,
+    #[cfg(feature = "gzip")]
+    gzip: Option<bool>,
+    headers: HeaderMap,
+    dry_run: bool,
+    debug_payloads: bool,
+    key_reveal: Option<usize>,
+    request_hook: Option<RequestHook<reqwest::blocking::RequestBuilder>>,
     client: Option<reqwest::blocking::Client>,
 }
 
@@ -161,21 +823,37 @@ impl WeComBotBuilder {
 
     /// Returns a `WeComBot` client that use this `WeComBotBuilder` configuration.
     pub fn build(self) -> WeComResult<WeComBot> {
-        let (url, upload_base_url) = format_wecom_url!(self.key);
+        let (url, upload_base_url) = format_wecom_url!(self.base_url, self.key, self.allow_any_key);
 
-        let client = self.client.unwrap_or(
-            reqwest::blocking::Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .unwrap(),
-        );
+        let client = match self.client {
+            Some(client) => client,
+            None => build_default_client!(reqwest::blocking::Client::builder(), self),
+        };
         Ok(WeComBot {
             client,
             url,
             upload_base_url,
+            upload_field_name: self
+                .upload_field_name
+                .unwrap_or_else(|| String::from(DEFAULT_UPLOAD_FIELD_NAME)),
+            max_text_bytes: self.max_text_bytes.unwrap_or(TEXT_MAX_BYTES),
+            max_markdown_bytes: self.max_markdown_bytes.unwrap_or(MARKDOWN_MAX_BYTES),
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff.unwrap_or(DEFAULT_RETRY_BACKOFF),
+            headers: self.headers,
+            dry_run: self.dry_run.then(LastSent::default),
+            debug_payloads: self.debug_payloads,
+            key_reveal: self.key_reveal.unwrap_or(DEFAULT_KEY_REVEAL),
+            request_hook: self.request_hook,
         })
     }
 
+    /// Same as `build` but keeps the builder, so that it can be fixed and
+    /// built again if the configuration is rejected, e.g. the key is missing.
+    pub fn try_build(&self) -> WeComResult<WeComBot> {
+        self.clone().build()
+    }
+
     /// Sets the wecom bot webhook key to be used by client to build url
     pub fn key<K>(mut self, key: K) -> WeComBotBuilder
     where
@@ -185,86 +863,622 @@ impl WeComBotBuilder {
         self
     }
 
-    pub fn client(mut self, client: reqwest::blocking::Client) -> WeComBotBuilder {
-        self.client = Some(client);
+    /// Sets the wecom bot webhook key from a full webhook url copied from
+    /// WeCom, e.g. `https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=xxx`.
+    ///
+    /// `build` returns `WeComError::KeyNotFound` if the url has no `key`.
+    pub fn webhook_url(mut self, url: &str) -> WeComBotBuilder {
+        self.key = webhook_key(url);
         self
     }
-}
 
-#[cfg(feature = "async_api")]
-pub struct WeComBotAsync {
-    url: String,
-    upload_base_url: String,
+    /// Skips checking the key has the shape of a wecom webhook key on `build`.
+    ///
+    /// Only an empty key is rejected when enabled, defaults to false.
+    pub fn allow_any_key(mut self, allow: bool) -> WeComBotBuilder {
+        self.allow_any_key = allow;
+        self
+    }
 
-    client: reqwest::Client,
-}
+    /// Sets the total timeout of each request, defaults to 10 seconds.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn timeout(mut self, timeout: Duration) -> WeComBotBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
 
-#[cfg(feature = "async_api")]
-impl WeComBotAsync {
-    /// Returns the default configuration of `WecomBotAsync` client.
-    fn new<K>(key: K) -> WeComResult<WeComBotAsync>
-    where
-        K: Into<String>,
-    {
-        WeComBotAsyncBuilder::new().key(key).build()
+    /// Sets the timeout of connecting to the server only, so that unreachable
+    /// hosts fail fast while the whole request is still bound by `timeout`.
+    /// Not set by default.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> WeComBotBuilder {
+        self.connect_timeout = Some(timeout);
+        self
     }
 
-    /// Returns a `WeComBotAsyncBuilder` to configure a `WecomBotAsync` client.
-    pub fn builder() -> WeComBotAsyncBuilder {
-        WeComBotAsyncBuilder::new()
+    /// Sets how long an idle pooled connection is kept alive, defaults to
+    /// the 90 seconds of reqwest.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> WeComBotBuilder {
+        self.pool_idle_timeout = Some(timeout);
+        self
     }
 
-    /// Constructs the wecom bot `Message` and sends it to wecom bot API in async.
-    pub async fn send<T>(&self, msg: Message<'_>) -> WeComResult<T>
+    /// Sets the maximum of idle pooled connections kept to the server, not
+    /// limited by default.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> WeComBotBuilder {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Sets the proxy url all requests are sent through, e.g.
+    /// `http://127.0.0.1:8080`.
+    ///
+    /// `build` returns `WeComError::Network` if the url is invalid. Ignored if
+    /// a custom client is set by `client`.
+    pub fn proxy<U>(mut self, proxy: U) -> WeComBotBuilder
     where
-        T: DeserializeOwned,
+        U: Into<String>,
     {
-        let resp = self
-            .client
-            .post(&self.url)
-            .json(&msg)
-            .send()
-            .await
-            .map_err(WeComError::network)?;
-        let status = resp.status();
-        if status.is_server_error() {
-            return Err(WeComError::Http { status });
-        }
-
-        serde_json::from_slice::<T>(&resp.bytes().await?).map_err(WeComError::data_type::<T>)
+        self.proxy = Some(proxy.into());
+        self
     }
 
-    /// Constructs the file uploader to upload local file to the wecom bot server.
-    pub async fn upload<P>(&self, media_type: MediaType, path: P) -> WeComResult<UploadResp>
+    /// Sets the `User-Agent` header sent with every request, defaults to
+    /// `wecom_bot/<version>`.
+    ///
+    /// `build` returns `WeComError::Network` if it is not a valid header
+    /// value. Ignored if a custom client is set by `client`.
+    pub fn user_agent<U>(mut self, user_agent: U) -> WeComBotBuilder
     where
-        P: AsRef<Path> + Sized,
+        U: Into<String>,
     {
-        let content = tokio::fs::read(&path)
-            .await
-            .map_err(WeComError::load_file)?;
+        self.user_agent = Some(user_agent.into());
+        self
+    }
 
-        let filename = self.get_filename(path.as_ref());
-        let part = reqwest::multipart::Part::bytes(content).file_name(filename);
-        let form = reqwest::multipart::Form::new().part("filename", part);
-        let upload_url = media_type.format_upload_url(&self.upload_base_url);
+    /// Accepts invalid TLS certificates of the server, e.g. of a self-hosted
+    /// relay with a self-signed certificate, defaults to false.
+    ///
+    /// **This is insecure**, any certificate is trusted and the connection is
+    /// exposed to man-in-the-middle attacks, only enable it for development
+    /// or testing. Has no effect without a TLS feature, and ignored if a
+    /// custom client is set by `client`.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> WeComBotBuilder {
+        self.danger_accept_invalid_certs = accept;
+        self
+    }
 
-        let resp = self
-            .client
-            .post(upload_url)
-            .multipart(form)
-            .send()
-            .await
-            .map_err(WeComError::network)?;
-        let status = resp.status();
-        if status.is_server_error() {
-            return Err(WeComError::Http { status });
-        }
+    /// Sets whether to ask for gzip compressed responses and decompress them
+    /// transparently, enabled by default with the `gzip` feature.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    #[cfg(feature = "gzip")]
+    pub fn gzip(mut self, enable: bool) -> WeComBotBuilder {
+        self.gzip = Some(enable);
+        self
+    }
 
-        serde_json::from_slice::<UploadResp>(&resp.bytes().await?)
-            .map_err(WeComError::data_type::<UploadResp>)
+    /// Adds headers sent with every request, e.g. `X-Request-ID` required by a
+    /// gateway in front of the wecom bot API.
+    ///
+    /// Unlike the other client options, the headers are also sent when a
+    /// custom client is set by `client`.
+    pub fn default_headers(mut self, headers: HeaderMap) -> WeComBotBuilder {
+        self.headers.extend(headers);
+        self
     }
 
-    fn get_filename(&self, p: &Path) -> String {
+    /// Sets a hook applied to every send and upload request, e.g. to add a
+    /// query parameter the crate has no option for.
+    ///
+    /// The hook receives the request with the headers set, and may be called
+    /// more than once for a message if the request is retried.
+    pub fn request_hook<F>(mut self, hook: F) -> WeComBotBuilder
+    where
+        F: Fn(reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.request_hook = Some(RequestHook(Arc::new(hook)));
+        self
+    }
+
+    /// Skips the HTTP requests and returns successful responses instead, the
+    /// payload of the last sent message is kept for `last_sent`.
+    ///
+    /// Useful to test the message construction without reaching the wecom
+    /// bot API, defaults to false.
+    pub fn dry_run(mut self, dry_run: bool) -> WeComBotBuilder {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Logs the exact JSON payload of each sent message and the raw response
+    /// body as a debug `tracing` event, e.g. to look into a response that
+    /// could not be parsed. The webhook key is redacted from the logged url.
+    ///
+    /// Disabled by default, and only takes effect with the `tracing` feature.
+    pub fn debug_payloads(mut self, enable: bool) -> WeComBotBuilder {
+        self.debug_payloads = enable;
+        self
+    }
+
+    /// Sets the number of leading and trailing key chars shown by the `Debug`
+    /// output of the bot, the rest is masked by `****`. Defaults to 4, and 0
+    /// masks the key entirely.
+    pub fn debug_key_reveal(mut self, reveal: usize) -> WeComBotBuilder {
+        self.key_reveal = Some(reveal);
+        self
+    }
+
+    /// Sets the HTTP client used to send requests instead of the default one.
+    ///
+    /// The client options on this builder, such as `timeout`, are ignored when
+    /// a custom client is set.
+    pub fn client(mut self, client: reqwest::blocking::Client) -> WeComBotBuilder {
+        self.client = Some(client);
+        self
+    }
+
+    /// Same as `client` but sends through a clone of the shared `client`, so
+    /// that many bots share one connection pool instead of creating their own.
+    pub fn shared_client(self, client: &Arc<reqwest::blocking::Client>) -> WeComBotBuilder {
+        self.client(client.as_ref().clone())
+    }
+
+    /// Sets the base url of wecom bot API, defaults to
+    /// `https://qyapi.weixin.qq.com/cgi-bin/webhook`.
+    ///
+    /// Useful when the API is reached through a gateway.
+    pub fn base_url<U>(mut self, base_url: U) -> WeComBotBuilder
+    where
+        U: Into<String>,
+    {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Sets how many times a request is sent again after a transient network
+    /// error or a rate limited response with `errcode` 45009, defaults to 0.
+    ///
+    /// A rate limited request is sent again after the wait of the
+    /// `Retry-After` header, or the `retry_backoff` without one. Once the
+    /// retries are exhausted, it fails with `WeComError::RateLimited`.
+    pub fn max_retries(mut self, max_retries: usize) -> WeComBotBuilder {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay between retries, defaults to 500ms.
+    pub fn retry_backoff(mut self, backoff: Duration) -> WeComBotBuilder {
+        self.retry_backoff = Some(backoff);
+        self
+    }
+
+    /// Sets the multipart field name of the uploaded file, defaults to
+    /// `filename` as expected by wecom. Only needed if a gateway in front of
+    /// the wecom bot API requires another name.
+    pub fn upload_field_name<N>(mut self, name: N) -> WeComBotBuilder
+    where
+        N: Into<String>,
+    {
+        self.upload_field_name = Some(name.into());
+        self
+    }
+
+    /// Sets the maximum bytes of the content of a text message, defaults to
+    /// the 2048 bytes of wecom. A longer text is rejected with
+    /// `WeComError::ContentTooLong` before sending.
+    ///
+    /// Only needed if a proxy in front of the wecom bot API allows more. Pass
+    /// the same limit to `Message::text_lines_with_limit` and
+    /// `Message::validate_with_limits` to check messages ahead of sending.
+    pub fn max_text_bytes(mut self, max: usize) -> WeComBotBuilder {
+        self.max_text_bytes = Some(max);
+        self
+    }
+
+    /// Same as `max_text_bytes` but for markdown messages, defaults to the
+    /// 4096 bytes of wecom.
+    pub fn max_markdown_bytes(mut self, max: usize) -> WeComBotBuilder {
+        self.max_markdown_bytes = Some(max);
+        self
+    }
+}
+
+#[cfg(feature = "async_api")]
+#[derive(Clone)]
+pub struct WeComBotAsync {
+    url: String,
+    upload_base_url: String,
+    upload_field_name: String,
+    max_text_bytes: usize,
+    max_markdown_bytes: usize,
+    max_retries: usize,
+    retry_backoff: Duration,
+    headers: HeaderMap,
+    dry_run: Option<LastSent>,
+    debug_payloads: bool,
+    key_reveal: usize,
+    request_hook: Option<RequestHook<reqwest::RequestBuilder>>,
+
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "async_api")]
+impl WeComBotAsync {
+    /// Returns the default configuration of `WecomBotAsync` client.
+    fn new<K>(key: K) -> WeComResult<WeComBotAsync>
+    where
+        K: Into<String>,
+    {
+        WeComBotAsyncBuilder::new().key(key).build()
+    }
+
+    /// Returns a `WeComBotAsyncBuilder` to configure a `WecomBotAsync` client.
+    pub fn builder() -> WeComBotAsyncBuilder {
+        WeComBotAsyncBuilder::new()
+    }
+
+    /// Returns a `WeComBotAsync` with the default configuration that sends
+    /// through a clone of the shared `client`, so that many bots share one
+    /// connection pool instead of creating their own.
+    pub fn with_shared_client<K>(
+        key: K,
+        client: &Arc<reqwest::Client>,
+    ) -> WeComResult<WeComBotAsync>
+    where
+        K: Into<String>,
+    {
+        WeComBotAsyncBuilder::new()
+            .key(key)
+            .shared_client(client)
+            .build()
+    }
+
+    /// Returns a `WeComBotAsync` with the key read from the `WECOM_BOT_KEY` environment
+    /// variable, and the base url from `WECOM_BOT_BASE_URL` if it is set.
+    ///
+    /// Returns `WeComError::KeyNotFound` if `WECOM_BOT_KEY` is missing or empty.
+    pub fn from_env() -> WeComResult<WeComBotAsync> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    fn from_env_with<F>(lookup: F) -> WeComResult<WeComBotAsync>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let (key, base_url) = read_env(lookup)?;
+        let mut builder = WeComBotAsyncBuilder::new().key(key);
+        if let Some(base_url) = base_url {
+            builder = builder.base_url(base_url);
+        }
+        builder.build()
+    }
+
+    /// Returns the underlying HTTP client, e.g. to fetch an `Image` from url.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Returns the url messages are sent to.
+    ///
+    /// The url contains the secret webhook key, take care when logging it.
+    pub fn send_url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the url files are uploaded to, without the `type` parameter.
+    ///
+    /// The url contains the secret webhook key, take care when logging it.
+    pub fn upload_url_base(&self) -> &str {
+        &self.upload_base_url
+    }
+
+    /// Returns the JSON payload of the last message sent in dry run mode, or
+    /// `None` if nothing was sent or dry run is disabled.
+    pub fn last_sent(&self) -> Option<String> {
+        self.dry_run.as_ref()?.lock().unwrap().clone()
+    }
+
+    /// Constructs the wecom bot `Message` and sends it to wecom bot API in async.
+    pub async fn send<T>(&self, msg: Message<'_>) -> WeComResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        let (body, _) = self.send_body(msg).await?;
+        parse_body::<T>(&body)
+    }
+
+    /// Same as `send` but returns the default `SendResp`, without the need of
+    /// a type annotation.
+    ///
+    /// ```
+    /// # use wecom_bot::{Message, WeComBotAsync, WeComError};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), WeComError> {
+    /// let bot = WeComBotAsync::builder()
+    ///     .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+    ///     .dry_run(true)
+    ///     .build()?;
+    /// let rsp = bot.send_text_msg(Message::text("hello world!")).await?;
+    /// assert!(rsp.is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_text_msg(&self, msg: Message<'_>) -> WeComResult<SendResp> {
+        self.send(msg).await
+    }
+
+    /// Same as `send` but also returns the `SendMeta` of the request, e.g. to
+    /// account the payload size against the quota of wecom bot API.
+    pub async fn send_with_meta<T>(&self, msg: Message<'_>) -> WeComResult<(T, SendMeta)>
+    where
+        T: DeserializeOwned,
+    {
+        let (body, meta) = self.send_body(msg).await?;
+        Ok((parse_body::<T>(&body)?, meta))
+    }
+
+    /// Same as `send` but also returns the raw response body, e.g. to log
+    /// exactly what the wecom bot API returned.
+    pub async fn send_raw(&self, msg: Message<'_>) -> WeComResult<(String, SendResp)> {
+        let (body, _) = self.send_body(msg).await?;
+        let resp = parse_body::<SendResp>(&body)?;
+        Ok((String::from_utf8_lossy(&body).into_owned(), resp))
+    }
+
+    /// Sends the message in async and succeeds only if the wecom bot API
+    /// accepted it, a non-zero `errcode` is returned as `WeComError::Api`.
+    pub async fn notify(&self, msg: Message<'_>) -> WeComResult<()> {
+        check_send_resp(self.send::<SendResp>(msg).await?)
+    }
+
+    /// Posts the message and returns the response body.
+    async fn send_body(&self, msg: Message<'_>) -> WeComResult<(Vec<u8>, SendMeta)> {
+        let sent = async {
+            let body = self.post_message(&msg).await;
+            trace_sent(&msg, &body);
+            body
+        };
+        #[cfg(feature = "tracing")]
+        let sent = tracing::Instrument::instrument(
+            sent,
+            tracing::debug_span!("wecom_bot.send", msgtype = msg.msg_type()),
+        );
+        sent.await
+    }
+
+    async fn post_message(&self, msg: &Message<'_>) -> WeComResult<(Vec<u8>, SendMeta)> {
+        msg.check_content_limits(self.max_text_bytes, self.max_markdown_bytes)?;
+        let payload = msg.to_json().expect("message is always serializable");
+        if let Some(last_sent) = &self.dry_run {
+            return Ok(dry_run_send(last_sent, payload));
+        }
+
+        let payload_bytes = payload.len();
+        let mut attempt = 0;
+        loop {
+            let sent = self
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.clone())
+                .send()
+                .await;
+            let resp = match sent.map_err(WeComError::network) {
+                Ok(resp) => resp,
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_backoff).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.bytes().await.unwrap_or_default();
+                trace_payloads(
+                    self.debug_payloads,
+                    &self.url,
+                    self.key_reveal,
+                    &payload,
+                    &body,
+                );
+                return Err(WeComError::http(status, &body));
+            }
+
+            let meta = SendMeta {
+                payload_bytes,
+                status,
+            };
+            let headers = resp.headers().clone();
+            let body = resp.bytes().await?.to_vec();
+            trace_payloads(
+                self.debug_payloads,
+                &self.url,
+                self.key_reveal,
+                &payload,
+                &body,
+            );
+            match check_rate_limited(&headers, &body) {
+                Ok(()) => return Ok((body, meta)),
+                Err(WeComError::RateLimited { retry_after }) if attempt < self.max_retries => {
+                    attempt += 1;
+                    tokio::time::sleep(retry_after.unwrap_or(self.retry_backoff)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns whether the webhook key is accepted by the wecom bot API, or
+    /// `false` if it is rejected as an invalid webhook with `errcode` 93000.
+    /// Other API errors, including the rate limit, mean the key is valid.
+    ///
+    /// wecom has no request to only validate a key, so this posts a short
+    /// text message which is visible in the group if the key is valid.
+    pub async fn check_key(&self) -> WeComResult<bool> {
+        key_accepted(self.send(Message::text(CHECK_KEY_CONTENT)).await)
+    }
+
+    /// Sends the messages with at most `concurrency` requests in flight and
+    /// collects the result of each, in the same order as `msgs`. A failed
+    /// message does not stop the rest from being sent.
+    pub async fn send_all<'m, I>(&self, msgs: I, concurrency: usize) -> Vec<WeComResult<SendResp>>
+    where
+        I: IntoIterator<Item = Message<'m>>,
+    {
+        stream::iter(msgs)
+            .map(|msg| self.send(msg))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Sends the messages one by one and yields the result of each as soon as
+    /// it completes, in the same order as `msgs`. The next message is only
+    /// sent when the stream is polled again, so a slow consumer holds the
+    /// sending back.
+    pub fn send_stream<'a, I>(&'a self, msgs: I) -> impl Stream<Item = WeComResult<SendResp>> + 'a
+    where
+        I: IntoIterator<Item = Message<'a>>,
+        I::IntoIter: 'a,
+    {
+        stream::iter(msgs).then(move |msg| self.send(msg))
+    }
+
+    /// Sends `content` as text messages one by one in async, split into
+    /// chunks of no more than 2048 bytes by `Message::text_chunks`.
+    pub async fn send_chunked(&self, content: &str) -> Vec<WeComResult<SendResp>> {
+        self.send_all(Message::text_chunks(content), 1).await
+    }
+
+    /// Constructs the file uploader to upload local file to the wecom bot server.
+    pub async fn upload<P>(&self, media_type: MediaType, path: P) -> WeComResult<UploadResp>
+    where
+        P: AsRef<Path> + Sized,
+    {
+        let path = path.as_ref();
+        let uploaded = async {
+            let uploaded = self.upload_file(media_type, path).await;
+            trace_uploaded(media_type, path, &uploaded);
+            uploaded
+        };
+        #[cfg(feature = "tracing")]
+        let uploaded = tracing::Instrument::instrument(
+            uploaded,
+            tracing::debug_span!("wecom_bot.upload", media_type = media_type.as_str()),
+        );
+        uploaded.await
+    }
+
+    /// Same as `upload` but succeeds only if the wecom bot API accepted the
+    /// file, a non-zero `errcode` is returned as `WeComError::Api`, or as
+    /// `WeComError::MediaTooLarge` if the file exceeds the size limit.
+    pub async fn upload_checked<P>(&self, media_type: MediaType, path: P) -> WeComResult<UploadResp>
+    where
+        P: AsRef<Path>,
+    {
+        check_upload(self.upload(media_type, path).await?)
+    }
+
+    async fn upload_file(&self, media_type: MediaType, path: &Path) -> WeComResult<UploadResp> {
+        if self.dry_run.is_some() {
+            return Ok(dry_run_upload(media_type));
+        }
+
+        let filename = self.get_filename(path.as_ref());
+        let upload_url = media_type.format_upload_url(&self.upload_base_url);
+
+        let mut attempt = 0;
+        let resp = loop {
+            // the file is opened again for each attempt to upload it from the
+            // start, its content is streamed from disk instead of buffered
+            let file = tokio::fs::File::open(&path)
+                .await
+                .map_err(WeComError::load_file)?;
+            let len = file.metadata().await.map_err(WeComError::load_file)?.len();
+            let body = reqwest::Body::wrap_stream(ReaderStream::new(file));
+            let part =
+                reqwest::multipart::Part::stream_with_length(body, len).file_name(filename.clone());
+            let form = reqwest::multipart::Form::new().part(self.upload_field_name.clone(), part);
+
+            let sent = self.post(&upload_url).multipart(form).send().await;
+            match sent.map_err(WeComError::network) {
+                Ok(resp) => break resp,
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    tokio::time::sleep(self.retry_backoff).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(WeComError::http(
+                status,
+                &resp.bytes().await.unwrap_or_default(),
+            ));
+        }
+
+        let body = resp.bytes().await?;
+        serde_json::from_slice::<UploadResp>(&body)
+            .map_err(|e| WeComError::data_type::<UploadResp>(e, &body))
+    }
+
+    /// Uploads the local file then sends it as a file `Message` in async.
+    ///
+    /// Returns the error of `upload_checked` without sending if the upload is
+    /// rejected.
+    pub async fn upload_and_send_file<P>(&self, path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        let uploaded = self.upload(MediaType::File, path).await?;
+        let media_id = check_upload_resp(uploaded)?;
+        self.send(Message::file(media_id)).await
+    }
+
+    /// Uploads the local AMR file then sends it as a voice `Message` in async.
+    ///
+    /// Returns the error of `upload_checked` without sending if the upload is
+    /// rejected.
+    pub async fn upload_and_send_voice<P>(&self, path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        let uploaded = self.upload(MediaType::Voice, path).await?;
+        let media_id = check_upload_resp(uploaded)?;
+        self.send(Message::voice(media_id)).await
+    }
+
+    /// Always returns `WeComError::NotSendable`, the wecom bot API accepts
+    /// video uploads but has no video message to send them with.
+    ///
+    /// Use `upload` with `MediaType::Video`, or send the video as a file.
+    pub async fn upload_and_send_video<P>(&self, _path: P) -> WeComResult<SendResp>
+    where
+        P: AsRef<Path>,
+    {
+        Err(WeComError::NotSendable(MediaType::Video))
+    }
+
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        let builder = self.client.post(url).headers(self.headers.clone());
+        match &self.request_hook {
+            Some(hook) => (hook.0)(builder),
+            None => builder,
+        }
+    }
+
+    fn get_filename(&self, p: &Path) -> String {
         let name = match p.file_name() {
             None => "",
             Some(f) => f.to_str().unwrap(),
@@ -276,14 +1490,38 @@ impl WeComBotAsync {
 #[cfg(feature = "async_api")]
 impl Debug for WeComBotAsync {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("WeComBot").field("url", &self.url).finish()
+        f.debug_struct("WeComBotAsync")
+            .field("url", &redact_key(&self.url, self.key_reveal))
+            .finish()
     }
 }
 
 #[cfg(feature = "async_api")]
-#[derive(Debug, Default)]
+#[must_use = "builders do nothing unless `build` is called"]
+#[derive(Debug, Default, Clone)]
 pub struct WeComBotAsyncBuilder {
     key: Option<String>,
+    allow_any_key: bool,
+    base_url: Option<String>,
+    upload_field_name: Option<String>,
+    max_text_bytes: Option<usize>,
+    max_markdown_bytes: Option<usize>,
+    max_retries: usize,
+    retry_backoff: Option<Duration>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    danger_accept_invalid_certs: bool,
+    #[cfg(feature = "gzip")]
+    gzip: Option<bool>,
+    headers: HeaderMap,
+    dry_run: bool,
+    debug_payloads: bool,
+    key_reveal: Option<usize>,
+    request_hook: Option<RequestHook<reqwest::RequestBuilder>>,
     client: Option<reqwest::Client>,
 }
 
@@ -299,22 +1537,38 @@ impl WeComBotAsyncBuilder {
     /// Returns a `WeComBotAsync` client that use this `WeComBotAsyncBuilder`
     /// configuration.
     pub fn build(self) -> WeComResult<WeComBotAsync> {
-        let (url, upload_base_url) = format_wecom_url!(self.key);
+        let (url, upload_base_url) = format_wecom_url!(self.base_url, self.key, self.allow_any_key);
 
-        let client = self.client.unwrap_or(
-            reqwest::Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .unwrap(),
-        );
+        let client = match self.client {
+            Some(client) => client,
+            None => build_default_client!(reqwest::Client::builder(), self),
+        };
 
         Ok(WeComBotAsync {
             client,
             url,
             upload_base_url,
+            upload_field_name: self
+                .upload_field_name
+                .unwrap_or_else(|| String::from(DEFAULT_UPLOAD_FIELD_NAME)),
+            max_text_bytes: self.max_text_bytes.unwrap_or(TEXT_MAX_BYTES),
+            max_markdown_bytes: self.max_markdown_bytes.unwrap_or(MARKDOWN_MAX_BYTES),
+            max_retries: self.max_retries,
+            retry_backoff: self.retry_backoff.unwrap_or(DEFAULT_RETRY_BACKOFF),
+            headers: self.headers,
+            dry_run: self.dry_run.then(LastSent::default),
+            debug_payloads: self.debug_payloads,
+            key_reveal: self.key_reveal.unwrap_or(DEFAULT_KEY_REVEAL),
+            request_hook: self.request_hook,
         })
     }
 
+    /// Same as `build` but keeps the builder, so that it can be fixed and
+    /// built again if the configuration is rejected, e.g. the key is missing.
+    pub fn try_build(&self) -> WeComResult<WeComBotAsync> {
+        self.clone().build()
+    }
+
     /// Sets the wecom bot webhook key to be used by client to build url
     pub fn key<K>(mut self, key: K) -> WeComBotAsyncBuilder
     where
@@ -324,61 +1578,1837 @@ impl WeComBotAsyncBuilder {
         self
     }
 
-    pub fn client(mut self, client: reqwest::Client) -> WeComBotAsyncBuilder {
-        self.client = Some(client);
+    /// Sets the wecom bot webhook key from a full webhook url copied from
+    /// WeCom, e.g. `https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=xxx`.
+    ///
+    /// `build` returns `WeComError::KeyNotFound` if the url has no `key`.
+    pub fn webhook_url(mut self, url: &str) -> WeComBotAsyncBuilder {
+        self.key = webhook_key(url);
         self
     }
-}
-
-#[cfg(test)]
-mod botest {
-    use crate::message::Message;
-    use crate::response::SendResp;
-    use std::fs::read;
-    use std::io;
-
-    use super::WeComBot;
 
-    fn read_wecom_bot_key() -> Result<String, io::Error> {
-        let key = match read("src/tests/secrets/key") {
-            Ok(v) => {
-                String::from_utf8(v).map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
-            }
-            Err(_) => std::env::var("WECOM_BOT_KEY")
-                .map_err(|_| io::Error::from(io::ErrorKind::NotFound))?,
-        };
-        Ok(key)
+    /// Skips checking the key has the shape of a wecom webhook key on `build`.
+    ///
+    /// Only an empty key is rejected when enabled, defaults to false.
+    pub fn allow_any_key(mut self, allow: bool) -> WeComBotAsyncBuilder {
+        self.allow_any_key = allow;
+        self
     }
 
-    #[test]
-    fn send_msg() {
-        let bot = WeComBot::new(read_wecom_bot_key().unwrap()).unwrap();
-        let resp: SendResp = bot
-            .send(Message::text(
-                "say hi to wecom bot power by rust".to_string(),
-            ))
-            .unwrap();
+    /// Sets the total timeout of each request, defaults to 10 seconds.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn timeout(mut self, timeout: Duration) -> WeComBotAsyncBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
 
-        assert_eq!(resp.err_code, 0);
+    /// Sets the timeout of connecting to the server only, so that unreachable
+    /// hosts fail fast while the whole request is still bound by `timeout`.
+    /// Not set by default.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> WeComBotAsyncBuilder {
+        self.connect_timeout = Some(timeout);
+        self
     }
 
-    #[test]
-    fn upload_media() {
-        let bot = WeComBot::new(read_wecom_bot_key().unwrap()).unwrap();
-        let resp = bot
-            .upload(
-                crate::MediaType::File,
-                "./src/tests/imgs/tiny-rust-logo.png",
-            )
-            .unwrap();
+    /// Sets how long an idle pooled connection is kept alive, defaults to
+    /// the 90 seconds of reqwest.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> WeComBotAsyncBuilder {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
 
-        assert_eq!(resp.err_code, 0);
-        assert_ne!(resp.media_id, "");
+    /// Sets the maximum of idle pooled connections kept to the server, not
+    /// limited by default.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> WeComBotAsyncBuilder {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Sets the proxy url all requests are sent through, e.g.
+    /// `http://127.0.0.1:8080`.
+    ///
+    /// `build` returns `WeComError::Network` if the url is invalid. Ignored if
+    /// a custom client is set by `client`.
+    pub fn proxy<U>(mut self, proxy: U) -> WeComBotAsyncBuilder
+    where
+        U: Into<String>,
+    {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request, defaults to
+    /// `wecom_bot/<version>`.
+    ///
+    /// `build` returns `WeComError::Network` if it is not a valid header
+    /// value. Ignored if a custom client is set by `client`.
+    pub fn user_agent<U>(mut self, user_agent: U) -> WeComBotAsyncBuilder
+    where
+        U: Into<String>,
+    {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Accepts invalid TLS certificates of the server, e.g. of a self-hosted
+    /// relay with a self-signed certificate, defaults to false.
+    ///
+    /// **This is insecure**, any certificate is trusted and the connection is
+    /// exposed to man-in-the-middle attacks, only enable it for development
+    /// or testing. Has no effect without a TLS feature, and ignored if a
+    /// custom client is set by `client`.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> WeComBotAsyncBuilder {
+        self.danger_accept_invalid_certs = accept;
+        self
+    }
+
+    /// Sets whether to ask for gzip compressed responses and decompress them
+    /// transparently, enabled by default with the `gzip` feature.
+    ///
+    /// Ignored if a custom client is set by `client`.
+    #[cfg(feature = "gzip")]
+    pub fn gzip(mut self, enable: bool) -> WeComBotAsyncBuilder {
+        self.gzip = Some(enable);
+        self
+    }
+
+    /// Adds headers sent with every request, e.g. `X-Request-ID` required by a
+    /// gateway in front of the wecom bot API.
+    ///
+    /// Unlike the other client options, the headers are also sent when a
+    /// custom client is set by `client`.
+    pub fn default_headers(mut self, headers: HeaderMap) -> WeComBotAsyncBuilder {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Sets a hook applied to every send and upload request, e.g. to add a
+    /// query parameter the crate has no option for.
+    ///
+    /// The hook receives the request with the headers set, and may be called
+    /// more than once for a message if the request is retried.
+    pub fn request_hook<F>(mut self, hook: F) -> WeComBotAsyncBuilder
+    where
+        F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder + Send + Sync + 'static,
+    {
+        self.request_hook = Some(RequestHook(Arc::new(hook)));
+        self
+    }
+
+    /// Skips the HTTP requests and returns successful responses instead, the
+    /// payload of the last sent message is kept for `last_sent`.
+    ///
+    /// Useful to test the message construction without reaching the wecom
+    /// bot API, defaults to false.
+    pub fn dry_run(mut self, dry_run: bool) -> WeComBotAsyncBuilder {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Logs the exact JSON payload of each sent message and the raw response
+    /// body as a debug `tracing` event, e.g. to look into a response that
+    /// could not be parsed. The webhook key is redacted from the logged url.
+    ///
+    /// Disabled by default, and only takes effect with the `tracing` feature.
+    pub fn debug_payloads(mut self, enable: bool) -> WeComBotAsyncBuilder {
+        self.debug_payloads = enable;
+        self
+    }
+
+    /// Sets the number of leading and trailing key chars shown by the `Debug`
+    /// output of the bot, the rest is masked by `****`. Defaults to 4, and 0
+    /// masks the key entirely.
+    pub fn debug_key_reveal(mut self, reveal: usize) -> WeComBotAsyncBuilder {
+        self.key_reveal = Some(reveal);
+        self
+    }
+
+    /// Sets the HTTP client used to send requests instead of the default one.
+    ///
+    /// The client options on this builder, such as `timeout`, are ignored when
+    /// a custom client is set.
+    pub fn client(mut self, client: reqwest::Client) -> WeComBotAsyncBuilder {
+        self.client = Some(client);
+        self
+    }
+
+    /// Same as `client` but sends through a clone of the shared `client`, so
+    /// that many bots share one connection pool instead of creating their own.
+    pub fn shared_client(self, client: &Arc<reqwest::Client>) -> WeComBotAsyncBuilder {
+        self.client(client.as_ref().clone())
+    }
+
+    /// Sets the base url of wecom bot API, defaults to
+    /// `https://qyapi.weixin.qq.com/cgi-bin/webhook`.
+    ///
+    /// Useful when the API is reached through a gateway.
+    pub fn base_url<U>(mut self, base_url: U) -> WeComBotAsyncBuilder
+    where
+        U: Into<String>,
+    {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Sets how many times a request is sent again after a transient network
+    /// error or a rate limited response with `errcode` 45009, defaults to 0.
+    ///
+    /// A rate limited request is sent again after the wait of the
+    /// `Retry-After` header, or the `retry_backoff` without one. Once the
+    /// retries are exhausted, it fails with `WeComError::RateLimited`.
+    pub fn max_retries(mut self, max_retries: usize) -> WeComBotAsyncBuilder {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay between retries, defaults to 500ms.
+    pub fn retry_backoff(mut self, backoff: Duration) -> WeComBotAsyncBuilder {
+        self.retry_backoff = Some(backoff);
+        self
+    }
+
+    /// Sets the multipart field name of the uploaded file, defaults to
+    /// `filename` as expected by wecom. Only needed if a gateway in front of
+    /// the wecom bot API requires another name.
+    pub fn upload_field_name<N>(mut self, name: N) -> WeComBotAsyncBuilder
+    where
+        N: Into<String>,
+    {
+        self.upload_field_name = Some(name.into());
+        self
+    }
+
+    /// Sets the maximum bytes of the content of a text message, defaults to
+    /// the 2048 bytes of wecom. A longer text is rejected with
+    /// `WeComError::ContentTooLong` before sending.
+    ///
+    /// Only needed if a proxy in front of the wecom bot API allows more. Pass
+    /// the same limit to `Message::text_lines_with_limit` and
+    /// `Message::validate_with_limits` to check messages ahead of sending.
+    pub fn max_text_bytes(mut self, max: usize) -> WeComBotAsyncBuilder {
+        self.max_text_bytes = Some(max);
+        self
+    }
+
+    /// Same as `max_text_bytes` but for markdown messages, defaults to the
+    /// 4096 bytes of wecom.
+    pub fn max_markdown_bytes(mut self, max: usize) -> WeComBotAsyncBuilder {
+        self.max_markdown_bytes = Some(max);
+        self
+    }
+}
+
+#[cfg(test)]
+mod botest {
+    use crate::media::MediaType;
+    use crate::message::Message;
+    use crate::response::SendResp;
+    use serde::Deserialize;
+    use std::fs::read;
+    use std::io;
+
+    use super::{is_retryable, parse_body, WeComBot, WeComError};
+    use crate::tests::{MockServer, Reply};
+    use std::time::Duration;
+
+    fn read_wecom_bot_key() -> Result<String, io::Error> {
+        let key = match read("src/tests/secrets/key") {
+            Ok(v) => {
+                String::from_utf8(v).map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
+            }
+            Err(_) => std::env::var("WECOM_BOT_KEY")
+                .map_err(|_| io::Error::from(io::ErrorKind::NotFound))?,
+        };
+        Ok(key)
+    }
+
+    #[test]
+    fn parse_api_error() {
+        #[derive(Debug, Deserialize)]
+        struct Custom {
+            #[serde(rename = "msgid")]
+            _msg_id: String,
+        }
+
+        let body = br#"{"errcode":93000,"errmsg":"invalid webhook url"}"#;
+        match parse_body::<Custom>(body) {
+            Err(WeComError::Api { err_code, err_msg }) => {
+                assert_eq!(err_code, 93000);
+                assert_eq!(err_msg, "invalid webhook url");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let body = br#"{"errcode":0,"errmsg":"ok"}"#;
+        assert!(matches!(
+            parse_body::<Custom>(body),
+            Err(WeComError::DataType { .. })
+        ));
+        assert!(matches!(
+            parse_body::<Custom>(b"<html></html>"),
+            Err(WeComError::DataType { .. })
+        ));
+    }
+
+    #[test]
+    fn error_accessors() {
+        let err = WeComError::Http {
+            status: reqwest::StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        };
+        assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(!err.is_network());
+        assert_eq!(err.api_code(), None);
+
+        let err = WeComError::Api {
+            err_code: 93000,
+            err_msg: "invalid webhook url".to_string(),
+        };
+        assert_eq!(err.status(), None);
+        assert_eq!(err.api_code(), Some(93000));
+
+        let source = reqwest::blocking::Client::new()
+            .get("not a url")
+            .send()
+            .unwrap_err();
+        let err = WeComError::from(source);
+        assert!(err.is_network());
+        assert_eq!(err.status(), None);
+        assert_eq!(err.api_code(), None);
+
+        let err = WeComError::KeyNotFound;
+        assert!(!err.is_network());
+        assert_eq!(err.status(), None);
+    }
+
+    #[test]
+    fn send_client_error() {
+        let server = MockServer::start(vec![Reply::json(404, r#"{"errcode":0}"#)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let err = bot.send::<SendResp>(Message::text("404")).unwrap_err();
+        assert!(matches!(err, WeComError::Http { .. }));
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+
+        let err = bot
+            .upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png",
+            )
+            .unwrap_err();
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_client_error_async() {
+        let server = MockServer::start(vec![Reply::json(401, r#"{"errcode":0}"#)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let err = bot
+            .send::<SendResp>(Message::text("401"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn build_invalid_key() {
+        assert!(matches!(
+            WeComBot::builder().key("not-a-key").build(),
+            Err(WeComError::InvalidKey)
+        ));
+        assert!(matches!(
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aa")
+                .build(),
+            Err(WeComError::InvalidKey)
+        ));
+        assert!(matches!(
+            WeComBot::builder().key(" ").allow_any_key(true).build(),
+            Err(WeComError::KeyNotFound)
+        ));
+
+        assert!(WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .build()
+            .is_ok());
+        assert!(WeComBot::builder()
+            .key("not-a-key")
+            .allow_any_key(true)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn build_from_webhook_url() {
+        let bot = WeComBot::builder()
+            .webhook_url("https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .build()
+            .unwrap();
+        assert_eq!(
+            bot.url,
+            "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"
+        );
+
+        let bot = WeComBot::builder()
+            .webhook_url("https://qyapi.weixin.qq.com/cgi-bin/webhook/upload_media?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa&type=file")
+            .build()
+            .unwrap();
+        assert_eq!(
+            bot.upload_base_url,
+            "https://qyapi.weixin.qq.com/cgi-bin/webhook/upload_media?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"
+        );
+
+        assert!(matches!(
+            WeComBot::builder()
+                .webhook_url("https://qyapi.weixin.qq.com/cgi-bin/webhook/send")
+                .build(),
+            Err(WeComError::KeyNotFound)
+        ));
+    }
+
+    const SEND_OK: &str = r#"{"errcode":0,"errmsg":"ok"}"#;
+
+    #[test]
+    fn send_chunked() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let results = bot.send_chunked(&"日志".repeat(1000));
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn notify() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(200, r#"{"errcode":93000,"errmsg":"invalid key"}"#),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        assert!(bot.notify(Message::text("ok")).is_ok());
+        match bot.notify(Message::text("rejected")) {
+            Err(WeComError::Api { err_code, err_msg }) => {
+                assert_eq!(err_code, 93000);
+                assert_eq!(err_msg, "invalid key");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn notify_async() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(200, r#"{"errcode":93000,"errmsg":"invalid key"}"#),
+        ]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        assert!(bot.notify(Message::text("ok")).await.is_ok());
+        assert_eq!(
+            bot.notify(Message::text("rejected"))
+                .await
+                .unwrap_err()
+                .api_code(),
+            Some(93000)
+        );
+    }
+
+    #[test]
+    fn send_all() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(500, ""),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let results = bot.send_all(vec![
+            Message::text("first"),
+            Message::text("second"),
+            Message::text("third"),
+        ]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().err_code, 0);
+        assert!(matches!(results[1], Err(WeComError::Http { .. })));
+        assert_eq!(results[2].as_ref().unwrap().err_code, 0);
+
+        let bodies: Vec<String> = server.requests().iter().map(|r| r.body_str()).collect();
+        assert!(bodies[0].contains("first"));
+        assert!(bodies[1].contains("second"));
+        assert!(bodies[2].contains("third"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_all_async() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(500, ""),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let results = bot
+            .send_all(
+                vec![
+                    Message::text("first"),
+                    Message::text("second"),
+                    Message::text("third"),
+                ],
+                1,
+            )
+            .await;
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(WeComError::Http { .. })));
+        assert!(results[2].is_ok());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_stream_async() {
+        use futures_util::StreamExt;
+
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(500, ""),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let msgs = vec![
+            Message::text("first"),
+            Message::text("second"),
+            Message::text("third"),
+        ];
+        let results: Vec<_> = bot.send_stream(msgs).collect().await;
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(WeComError::Http { .. })));
+        assert!(results[2].is_ok());
+        assert!(server.requests()[2].body_str().contains("third"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_all_concurrency_async() {
+        let server = MockServer::start(vec![
+            Reply::json(200, r#"{"errcode":0,"errmsg":"0"}"#).delay(Duration::from_millis(300)),
+            Reply::json(200, SEND_OK).delay(Duration::from_millis(100)),
+        ]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let msgs = (0..5).map(|i| Message::text(i.to_string()));
+        let results = bot.send_all(msgs, 2).await;
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(server.requests().len(), 5);
+        assert!(server.max_in_flight() <= 2);
+    }
+
+    #[test]
+    fn send_timeout() {
+        let server =
+            MockServer::start(vec![Reply::json(200, SEND_OK).delay(Duration::from_secs(2))]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+        };
+
+        let bot = builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let err = bot.send::<SendResp>(Message::text("timeout")).unwrap_err();
+        match err {
+            WeComError::Network { source } => assert!(source.is_timeout()),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // the custom client wins over the timeout of builder
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let bot = builder()
+            .timeout(Duration::from_secs(10))
+            .client(client)
+            .build()
+            .unwrap();
+        let err = bot.send::<SendResp>(Message::text("timeout")).unwrap_err();
+        match err {
+            WeComError::Network { source } => assert!(source.is_timeout()),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn danger_accept_invalid_certs() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::text("insecure")).unwrap();
+        assert_eq!(resp.err_code, 0);
+    }
+
+    #[test]
+    fn send_connect_timeout() {
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url("http://10.255.255.1:81")
+            .connect_timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+
+        let start = std::time::Instant::now();
+        let err = bot
+            .send::<SendResp>(Message::text("unroutable"))
+            .unwrap_err();
+        assert!(err.is_network());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn send_content_limits() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+        };
+
+        let text = "x".repeat(3000);
+        match builder()
+            .build()
+            .unwrap()
+            .send::<SendResp>(Message::text(text.as_str()))
+        {
+            Err(WeComError::ContentTooLong { limit, actual }) => {
+                assert_eq!((limit, actual), (2048, 3000))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(server.requests().is_empty());
+
+        let resp: SendResp = builder()
+            .max_text_bytes(4096)
+            .build()
+            .unwrap()
+            .send(Message::text(text.as_str()))
+            .unwrap();
+        assert!(resp.is_ok());
+
+        let markdown = Message::markdown("x".repeat(100));
+        assert!(matches!(
+            builder()
+                .max_markdown_bytes(64)
+                .build()
+                .unwrap()
+                .send::<SendResp>(markdown),
+            Err(WeComError::ContentTooLong { limit: 64, .. })
+        ));
+    }
+
+    #[test]
+    fn try_build() {
+        let builder = WeComBot::builder().timeout(Duration::from_secs(3));
+        assert!(matches!(builder.try_build(), Err(WeComError::KeyNotFound)));
+
+        let builder = builder.key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa");
+        assert!(builder.try_build().is_ok());
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn send_pooled() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .pool_idle_timeout(Duration::from_secs(30))
+            .pool_max_idle_per_host(4)
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::text("pooled")).unwrap();
+        assert!(resp.is_ok());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn send_gzip() {
+        // `{"errcode":0,"errmsg":"ok"}` compressed by gzip
+        let compressed = vec![
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56, 0x4a, 0x2d,
+            0x2a, 0x4a, 0xce, 0x4f, 0x49, 0x55, 0xb2, 0x32, 0xd0, 0x01, 0xb1, 0x73, 0x8b, 0xd3,
+            0x95, 0xac, 0x94, 0xf2, 0xb3, 0x95, 0x6a, 0x01, 0xa7, 0x7e, 0x84, 0xf0, 0x1b, 0x00,
+            0x00, 0x00,
+        ];
+        let server =
+            MockServer::start(vec![Reply::bytes(200, "application/json", compressed)
+                .header("Content-Encoding", "gzip")]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .gzip(true)
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::text("gzip")).unwrap();
+        assert!(resp.is_ok());
+        assert!(server.requests()[0]
+            .header("accept-encoding")
+            .unwrap()
+            .contains("gzip"));
+    }
+
+    #[test]
+    fn send_through_proxy() {
+        let proxy = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url("http://wecom.invalid")
+            .proxy(proxy.url())
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::text("proxy")).unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert!(proxy.requests()[0]
+            .path
+            .starts_with("http://wecom.invalid/send?key="));
+
+        assert!(matches!(
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .proxy("http://[::1")
+                .build(),
+            Err(WeComError::Network { .. })
+        ));
+    }
+
+    #[test]
+    fn send_default_headers() {
+        let server =
+            MockServer::start(vec![Reply::json(200, SEND_OK), Reply::json(200, UPLOAD_OK)]);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("X-Request-ID", "req-1".parse().unwrap());
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .client(reqwest::blocking::Client::new())
+            .default_headers(headers)
+            .build()
+            .unwrap();
+
+        let _: SendResp = bot.send(Message::text("headers")).unwrap();
+        bot.upload(
+            crate::MediaType::File,
+            "./src/tests/imgs/tiny-rust-logo.png",
+        )
+        .unwrap();
+        for req in server.requests() {
+            assert_eq!(req.header("x-request-id"), Some("req-1"));
+        }
+    }
+
+    #[test]
+    fn request_hook() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .request_hook(|req| req.header("x-trace-id", "abc123"))
+            .build()
+            .unwrap();
+
+        let _: SendResp = bot.send(Message::text("hooked")).unwrap();
+        assert_eq!(server.requests()[0].header("x-trace-id"), Some("abc123"));
+    }
+
+    #[test]
+    fn send_raw_message() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let raw = r#"{"msgtype":"template_card","template_card":{"card_type":"text_notice"}}"#;
+        let msg = Message::raw(serde_json::from_str(raw).unwrap());
+        let _: SendResp = bot.send(msg).unwrap();
+        assert_eq!(server.requests()[0].body_str(), raw);
+    }
+
+    #[test]
+    fn send_rate_limited() {
+        let limited = r#"{"errcode":45009,"errmsg":"api freq out of limit"}"#;
+        let server = MockServer::start(vec![
+            Reply::json(200, limited).header("Retry-After", "0"),
+            Reply::json(200, SEND_OK),
+            Reply::json(200, limited),
+            Reply::json(200, limited).header("Retry-After", "30"),
+            Reply::json(200, limited),
+            Reply::json(200, limited),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .max_retries(1)
+            .retry_backoff(Duration::from_millis(10))
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::text("retried")).unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert_eq!(server.requests().len(), 2);
+
+        let started = std::time::Instant::now();
+        match bot.send::<SendResp>(Message::text("limited")) {
+            Err(WeComError::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_eq!(server.requests().len(), 4);
+
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        match bot.notify(Message::text("limited")) {
+            Err(e @ WeComError::RateLimited { retry_after: None }) => {
+                assert_eq!(e.api_code(), Some(45009))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[test]
+    fn check_key() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(200, r#"{"errcode":93000,"errmsg":"invalid webhook url"}"#),
+            Reply::json(200, r#"{"errcode":40008,"errmsg":"invalid message type"}"#),
+            Reply::json(500, "internal error"),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        assert!(bot.check_key().unwrap());
+        assert!(!bot.check_key().unwrap());
+        assert!(bot.check_key().unwrap());
+        assert!(bot.check_key().is_err());
+        assert!(server.requests()[0]
+            .body_str()
+            .contains("wecom_bot: webhook key check"));
+    }
+
+    #[test]
+    fn send_user_agent() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+        };
+
+        let _: SendResp = builder()
+            .build()
+            .unwrap()
+            .send(Message::text("ua"))
+            .unwrap();
+        let _: SendResp = builder()
+            .user_agent("ops-alerts/1.0")
+            .build()
+            .unwrap()
+            .send(Message::text("ua"))
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0].header("user-agent"),
+            Some(concat!("wecom_bot/", env!("CARGO_PKG_VERSION")))
+        );
+        assert_eq!(requests[1].header("user-agent"), Some("ops-alerts/1.0"));
+
+        assert!(matches!(
+            builder().user_agent("bad\nagent").build(),
+            Err(WeComError::Network { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_user_agent_async() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .user_agent("ops-alerts/1.0")
+            .build()
+            .unwrap();
+
+        let _: SendResp = bot.send(Message::text("ua")).await.unwrap();
+        assert_eq!(
+            server.requests()[0].header("user-agent"),
+            Some("ops-alerts/1.0")
+        );
+    }
+
+    #[test]
+    fn upload_and_send_file() {
+        let server =
+            MockServer::start(vec![Reply::json(200, UPLOAD_OK), Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload_and_send_file("./src/tests/imgs/tiny-rust-logo.png")
+            .unwrap();
+        assert_eq!(resp.err_code, 0);
+
+        let reqs = server.requests();
+        assert!(reqs[0].path.starts_with("/upload_media?"));
+        assert!(reqs[1].path.starts_with("/send?"));
+        assert_eq!(
+            reqs[1].body_str(),
+            r#"{"msgtype":"file","file":{"media_id":"1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0"}}"#
+        );
+
+        let server = MockServer::start(vec![Reply::json(
+            200,
+            r#"{"errcode":40009,"errmsg":"invalid media size"}"#,
+        )]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let err = bot
+            .upload_and_send_file("./src/tests/imgs/tiny-rust-logo.png")
+            .unwrap_err();
+        assert_eq!(err.api_code(), Some(40009));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn upload_and_send_file_async() {
+        let server =
+            MockServer::start(vec![Reply::json(200, UPLOAD_OK), Reply::json(200, SEND_OK)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload_and_send_file("./src/tests/imgs/tiny-rust-logo.png")
+            .await
+            .unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert!(server.requests()[1]
+            .body_str()
+            .contains("1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn upload_large_file_async() {
+        let path = std::env::temp_dir().join("wecom_bot_upload_large_file_async.bin");
+        let content: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &content).unwrap();
+
+        let server = MockServer::start(vec![Reply::json(200, UPLOAD_OK)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let resp = bot.upload(crate::MediaType::File, &path).await;
+        std::fs::remove_file(&path).unwrap();
+        assert!(resp.unwrap().is_ok());
+
+        let request = &server.requests()[0];
+        assert!(request.body.len() > content.len());
+        assert!(request
+            .body
+            .windows(content.len())
+            .any(|w| w == content.as_slice()));
+        assert!(request
+            .body_str()
+            .contains(r#"filename="wecom_bot_upload_large_file_async.bin""#));
+    }
+
+    #[test]
+    fn upload_and_send_voice() {
+        let upload_ok = UPLOAD_OK.replace(r#""type":"file""#, r#""type":"voice""#);
+        let server = MockServer::start(vec![
+            Reply::json(200, &upload_ok),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload_and_send_voice("./src/tests/imgs/tiny-rust-logo.png")
+            .unwrap();
+        assert_eq!(resp.err_code, 0);
+
+        let requests = server.requests();
+        assert!(requests[0].path.ends_with("&type=voice"));
+        assert!(requests[1].body_str().contains(r#""msgtype":"voice""#));
+        assert!(requests[1]
+            .body_str()
+            .contains("1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0"));
+    }
+
+    #[test]
+    fn upload_with_progress() {
+        let server = MockServer::start(vec![Reply::json(200, UPLOAD_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let file = "./src/tests/imgs/tiny-rust-logo.png";
+        let total = std::fs::metadata(file).unwrap().len();
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&calls);
+        bot.upload_with_progress(MediaType::File, file, move |sent, total| {
+            recorded.lock().unwrap().push((sent, total))
+        })
+        .unwrap();
+
+        let calls = calls.lock().unwrap();
+        assert!(!calls.is_empty());
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(calls.iter().all(|&(_, t)| t == total));
+        assert_eq!(calls.last().unwrap().0, total);
+        assert!(server.requests()[0]
+            .body_str()
+            .contains("tiny-rust-logo.png"));
+    }
+
+    #[test]
+    fn upload_checked() {
+        let server = MockServer::start(vec![
+            Reply::json(200, UPLOAD_OK),
+            Reply::json(200, r#"{"errcode":40009,"errmsg":"invalid media size"}"#),
+            Reply::json(200, r#"{"errcode":40004,"errmsg":"invalid media type"}"#),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let file = "./src/tests/imgs/tiny-rust-logo.png";
+        let resp = bot.upload_checked(MediaType::File, file).unwrap();
+        assert_eq!(
+            resp.media_id,
+            "1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0"
+        );
+        match bot.upload_checked(MediaType::File, file) {
+            Err(e @ WeComError::MediaTooLarge { .. }) => assert_eq!(e.api_code(), Some(40009)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            bot.upload_checked(MediaType::File, file),
+            Err(WeComError::Api {
+                err_code: 40004,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn upload_field_name() {
+        let server = MockServer::start(vec![
+            Reply::json(200, UPLOAD_OK),
+            Reply::json(200, UPLOAD_OK),
+        ]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+        };
+
+        let file = "./src/tests/imgs/tiny-rust-logo.png";
+        builder()
+            .build()
+            .unwrap()
+            .upload(MediaType::File, file)
+            .unwrap();
+        builder()
+            .upload_field_name("media")
+            .build()
+            .unwrap()
+            .upload(MediaType::File, file)
+            .unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].body_str().contains(r#"name="filename""#));
+        assert!(requests[1].body_str().contains(r#"name="media""#));
+    }
+
+    #[test]
+    fn upload_and_send_video() {
+        let server = MockServer::start(vec![Reply::json(200, UPLOAD_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let err = bot
+            .upload_and_send_video("./src/tests/imgs/tiny-rust-logo.png")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            WeComError::NotSendable(crate::MediaType::Video)
+        ));
+        assert!(err.to_string().contains("only be uploaded"));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn upload_and_send_voice_async() {
+        let server =
+            MockServer::start(vec![Reply::json(200, UPLOAD_OK), Reply::json(200, SEND_OK)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload_and_send_voice("./src/tests/imgs/tiny-rust-logo.png")
+            .await
+            .unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert!(server.requests()[1]
+            .body_str()
+            .contains(r#""msgtype":"voice""#));
+        assert!(matches!(
+            bot.upload_and_send_video("./src/tests/imgs/tiny-rust-logo.png")
+                .await,
+            Err(WeComError::NotSendable(crate::MediaType::Video))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    #[tracing_test::traced_test]
+    fn send_traced() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let msg = Message::text("traced");
+        let payload_bytes = msg.to_json().unwrap().len();
+        let _: SendResp = bot.send(msg).unwrap();
+
+        assert!(logs_contain("wecom bot message sent"));
+        assert!(logs_contain("msgtype=\"text\""));
+        assert!(logs_contain(&format!("payload_bytes={}", payload_bytes)));
+        assert!(logs_contain("errcode=0"));
+        assert!(!logs_contain("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    #[tracing_test::traced_test]
+    fn send_debug_payloads() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(200, r#"{"errcode":0,"errmsg":"payload logged"}"#),
+        ]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+        };
+
+        let _: SendResp = builder()
+            .build()
+            .unwrap()
+            .send(Message::text("quiet"))
+            .unwrap();
+        assert!(!logs_contain("wecom bot payloads"));
+
+        let _: SendResp = builder()
+            .debug_payloads(true)
+            .build()
+            .unwrap()
+            .send(Message::text("logged"))
+            .unwrap();
+        assert!(logs_contain("wecom bot payloads"));
+        assert!(logs_contain(
+            r#"{"msgtype":"text","text":{"content":"logged"}}"#
+        ));
+        assert!(logs_contain("payload logged"));
+        assert!(logs_contain("key=693a****5aaa"));
+        assert!(!logs_contain("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    #[tracing_test::traced_test]
+    fn send_debug_payloads_masked() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .debug_payloads(true)
+            .debug_key_reveal(0)
+            .build()
+            .unwrap();
+
+        let _: SendResp = bot.send(Message::text("masked")).unwrap();
+        assert!(logs_contain("wecom bot payloads"));
+        assert!(logs_contain("key=****"));
+        assert!(!logs_contain("693a"));
+    }
+
+    #[test]
+    fn send_with_meta() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let msg = Message::markdown("# 部署完成");
+        let payload = msg.to_json().unwrap();
+        let (resp, meta) = bot.send_with_meta::<SendResp>(msg).unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert_eq!(meta.payload_bytes, payload.len());
+        assert_eq!(meta.status, reqwest::StatusCode::OK);
+
+        let request = &server.requests()[0];
+        assert_eq!(request.body_str(), payload);
+        assert_eq!(request.header("content-type"), Some("application/json"));
+    }
+
+    #[test]
+    fn send_retry_after_reset() {
+        let server = MockServer::start(vec![Reply::Drop, Reply::Drop, Reply::json(200, SEND_OK)]);
+        let builder = || {
+            WeComBot::builder()
+                .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+                .base_url(server.url())
+                .retry_backoff(Duration::from_millis(10))
+        };
+
+        let bot = builder().max_retries(2).build().unwrap();
+        let resp: SendResp = bot.send(Message::text("retry")).unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert_eq!(server.requests().len(), 3);
+        for req in server.requests() {
+            assert!(req.body_str().contains("retry"));
+        }
+
+        // an HTTP error is not retried
+        let server = MockServer::start(vec![Reply::json(400, ""), Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .max_retries(2)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            bot.send::<SendResp>(Message::text("bad request")),
+            Err(WeComError::Http { .. })
+        ));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn retryable_errors() {
+        assert!(!is_retryable(&WeComError::http(
+            reqwest::StatusCode::BAD_REQUEST,
+            b""
+        )));
+        assert!(!is_retryable(&WeComError::Api {
+            err_code: 93000,
+            err_msg: String::from("invalid webhook url"),
+        }));
+        let parse = serde_json::from_str::<SendResp>("[]").unwrap_err();
+        assert!(!is_retryable(&WeComError::data_type::<SendResp>(
+            parse, b"[]"
+        )));
+    }
+
+    #[test]
+    fn from_env() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let url = server.url();
+        let bot = WeComBot::from_env_with(|name| match name {
+            "WECOM_BOT_KEY" => Some(String::from("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")),
+            "WECOM_BOT_BASE_URL" => Some(url.to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let resp: SendResp = bot.send(Message::text("env")).unwrap();
+        assert_eq!(resp.err_code, 0);
+        assert_eq!(server.requests().len(), 1);
+
+        for key in [None, Some(String::new())] {
+            assert!(matches!(
+                WeComBot::from_env_with(|_| key.clone()),
+                Err(WeComError::KeyNotFound)
+            ));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "async_api")]
+    fn from_env_async() {
+        let bot = super::WeComBotAsync::from_env_with(|name| {
+            (name == "WECOM_BOT_KEY").then(|| String::from("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"))
+        })
+        .unwrap();
+        assert!(bot.send_url().starts_with(super::WECOM_BASE_URL));
+        assert!(matches!(
+            super::WeComBotAsync::from_env_with(|_| None),
+            Err(WeComError::KeyNotFound)
+        ));
+    }
+
+    #[test]
+    fn send_raw() {
+        let body = r#"{"errcode":0,"errmsg":"ok","msgid":"abc"}"#;
+        let server = MockServer::start(vec![Reply::json(200, body), Reply::json(200, "[]")]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let (raw, resp) = bot.send_raw(Message::text("raw")).unwrap();
+        assert_eq!(raw, body);
+        assert_eq!(resp.err_code, 0);
+        assert_eq!(resp.err_msg, "ok");
+
+        match bot.send_raw(Message::text("raw")) {
+            Err(WeComError::DataType { body, .. }) => assert_eq!(body, "[]"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn with_shared_client() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let shared = std::sync::Arc::new(
+            reqwest::blocking::Client::builder()
+                .user_agent("shared-pool")
+                .build()
+                .unwrap(),
+        );
+
+        for key in [
+            "693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa",
+            "693a91f6-7xxx-4bc4-97a0-0ec2sifa5bbb",
+        ] {
+            let bot = WeComBot::builder()
+                .key(key)
+                .shared_client(&shared)
+                .base_url(server.url())
+                .build()
+                .unwrap();
+            let resp: SendResp = bot.send(Message::text("shared")).unwrap();
+            assert_eq!(resp.err_code, 0);
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].path.ends_with("5bbb"));
+        assert!(requests
+            .iter()
+            .all(|r| r.header("user-agent") == Some("shared-pool")));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn with_shared_client_async() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let shared = std::sync::Arc::new(
+            reqwest::Client::builder()
+                .user_agent("shared-pool")
+                .build()
+                .unwrap(),
+        );
+
+        for key in [
+            "693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa",
+            "693a91f6-7xxx-4bc4-97a0-0ec2sifa5bbb",
+        ] {
+            let bot = super::WeComBotAsync::builder()
+                .key(key)
+                .shared_client(&shared)
+                .base_url(server.url())
+                .build()
+                .unwrap();
+            let resp: SendResp = bot.send(Message::text("shared")).await.unwrap();
+            assert_eq!(resp.err_code, 0);
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].path.ends_with("5bbb"));
+        assert!(requests
+            .iter()
+            .all(|r| r.header("user-agent") == Some("shared-pool")));
+    }
+
+    #[test]
+    fn data_type_error_body() {
+        let server = MockServer::start(vec![Reply::bytes(
+            200,
+            "text/html",
+            b"<html>\xff</html>".to_vec(),
+        )]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let err = bot.send::<SendResp>(Message::text("html")).unwrap_err();
+        assert!(matches!(err, WeComError::DataType { .. }));
+        assert!(err.to_string().contains("body: <html>\u{fffd}</html>"));
+
+        let err = bot
+            .upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png",
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("<html>"));
+
+        let long = "字".repeat(300);
+        match parse_body::<SendResp>(long.as_bytes()) {
+            Err(WeComError::DataType { body, .. }) => {
+                assert!(body.len() <= crate::error::MAX_ERROR_BODY_LEN + 3);
+                assert!(body.ends_with("字..."));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn io_errors() {
+        use std::error::Error;
+
+        let err = match crate::Image::from_file("src/tests/imgs/not-found.png") {
+            Err(err) => err,
+            Ok(_) => panic!("image file should not exist"),
+        };
+        assert!(matches!(
+            err,
+            WeComError::Io {
+                kind: crate::IoKind::Image,
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("failed to read image file: "));
+        assert!(err.source().is_some());
+
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .build()
+            .unwrap();
+        let err = bot
+            .upload(crate::MediaType::File, "src/tests/imgs/not-found.png")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            WeComError::Io {
+                kind: crate::IoKind::Upload,
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("failed to read upload file: "));
+
+        fn read() -> Result<Vec<u8>, WeComError> {
+            Ok(std::fs::read("src/tests/imgs/not-found.png")?)
+        }
+        let err = read().unwrap_err();
+        assert!(err.to_string().starts_with("io error: "));
+        assert_eq!(
+            err.source()
+                .and_then(|e| e.downcast_ref::<io::Error>())
+                .map(io::Error::kind),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn clone_bot() {
+        let server = MockServer::start(vec![Reply::json(200, SEND_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        let cloned = bot.clone();
+
+        let handle = std::thread::spawn(move || cloned.send::<SendResp>(Message::text("clone")));
+        assert_eq!(
+            bot.send::<SendResp>(Message::text("bot")).unwrap().err_code,
+            0
+        );
+        assert_eq!(handle.join().unwrap().unwrap().err_code, 0);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn inspect_urls() {
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url("http://127.0.0.1:8080/webhook/")
+            .build()
+            .unwrap();
+        assert_eq!(
+            bot.send_url(),
+            "http://127.0.0.1:8080/webhook/send?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"
+        );
+        assert_eq!(
+            bot.upload_url_base(),
+            "http://127.0.0.1:8080/webhook/upload_media?key=693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"
+        );
+
+        let debug = format!("{:?}", bot);
+        assert!(debug.contains("http://127.0.0.1:8080/webhook/send?key="));
+        assert!(!debug.contains("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"));
+    }
+
+    #[test]
+    fn encode_key() {
+        let bot = WeComBot::builder()
+            .key("gateway+token/1")
+            .allow_any_key(true)
+            .base_url("http://127.0.0.1:8080/webhook")
+            .build()
+            .unwrap();
+        assert_eq!(
+            bot.send_url(),
+            "http://127.0.0.1:8080/webhook/send?key=gateway%2Btoken%2F1"
+        );
+
+        let bot = WeComBot::builder()
+            .webhook_url("http://127.0.0.1:8080/webhook/send?key=gateway%2Btoken%2F1")
+            .allow_any_key(true)
+            .build()
+            .unwrap();
+        assert!(bot.send_url().ends_with("?key=gateway%2Btoken%2F1"));
+    }
+
+    #[test]
+    fn debug_redacts_key() {
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", bot);
+        assert_eq!(
+            debug,
+            r#"WeComBot { url: "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=693a****5aaa" }"#
+        );
+        assert!(!debug.contains("7xxx-4bc4-97a0"));
+
+        let short = WeComBot::builder()
+            .key("short")
+            .allow_any_key(true)
+            .build()
+            .unwrap();
+        assert!(format!("{:?}", short).ends_with(r#"send?key=****" }"#));
+    }
+
+    #[test]
+    fn debug_key_reveal() {
+        let builder = || WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa");
+
+        let masked = format!("{:?}", builder().debug_key_reveal(0).build().unwrap());
+        assert!(masked.ends_with(r#"send?key=****" }"#));
+
+        let revealed = format!("{:?}", builder().debug_key_reveal(4).build().unwrap());
+        assert!(revealed.ends_with(r#"send?key=693a****5aaa" }"#));
+
+        let revealed = format!("{:?}", builder().debug_key_reveal(8).build().unwrap());
+        assert!(revealed.ends_with(r#"send?key=693a91f6****sifa5aaa" }"#));
+
+        let masked = format!(
+            "{:?}",
+            builder().debug_key_reveal(usize::MAX).build().unwrap()
+        );
+        assert!(masked.ends_with(r#"send?key=****" }"#));
+    }
+
+    #[test]
+    #[cfg(feature = "async_api")]
+    fn debug_redacts_key_async() {
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", bot);
+        assert!(debug.starts_with("WeComBotAsync"));
+        assert!(debug.contains("693a****5aaa"));
+        assert!(!debug.contains("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa"));
+    }
+
+    #[test]
+    fn send_dry_run() {
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url("http://wecom.invalid")
+            .dry_run(true)
+            .build()
+            .unwrap();
+        assert_eq!(bot.last_sent(), None);
+
+        let resp: SendResp = bot
+            .send(Message::text("dry run").mentioned_list(["@all"]))
+            .unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(
+            bot.last_sent().unwrap(),
+            r#"{"msgtype":"text","text":{"content":"dry run","mentioned_list":["@all"]}}"#
+        );
+
+        let resp = bot
+            .upload(crate::MediaType::Voice, "./src/tests/imgs/not-found.amr")
+            .unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(resp.media_type, "voice");
+
+        let resp = bot
+            .upload_and_send_file("./src/tests/imgs/tiny-rust-logo.png")
+            .unwrap();
+        assert!(resp.is_ok());
+        assert!(bot
+            .clone()
+            .last_sent()
+            .unwrap()
+            .contains(r#""msgtype":"file""#));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn send_dry_run_async() {
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url("http://wecom.invalid")
+            .dry_run(true)
+            .build()
+            .unwrap();
+
+        let resp: SendResp = bot.send(Message::markdown("dry run")).await.unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(
+            bot.last_sent().unwrap(),
+            r#"{"msgtype":"markdown","markdown":{"content":"dry run"}}"#
+        );
+    }
+
+    #[test]
+    fn http_error_body() {
+        let server = MockServer::start(vec![
+            Reply::json(500, r#"{"errcode":-1,"errmsg":"system busy"}"#),
+            Reply::json(503, ""),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let err = bot.send::<SendResp>(Message::text("500")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"wecom bot server error: 500 Internal Server Error - {"errcode":-1,"errmsg":"system busy"}"#
+        );
+
+        let err = bot.send::<SendResp>(Message::text("503")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "wecom bot server error: 503 Service Unavailable"
+        );
+    }
+
+    #[test]
+    fn send_msg() {
+        let bot = WeComBot::new(read_wecom_bot_key().unwrap()).unwrap();
+        let resp: SendResp = bot
+            .send(Message::text(
+                "say hi to wecom bot power by rust".to_string(),
+            ))
+            .unwrap();
+
+        assert_eq!(resp.err_code, 0);
+    }
+
+    #[test]
+    fn upload_media() {
+        let bot = WeComBot::new(read_wecom_bot_key().unwrap()).unwrap();
+        let resp = bot
+            .upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png",
+            )
+            .unwrap();
+
+        assert_eq!(resp.err_code, 0);
+        assert_ne!(resp.media_id, "");
 
         let resp: SendResp = bot.send(Message::file(resp.media_id)).unwrap();
         assert_eq!(resp.err_code, 0);
     }
 
+    const UPLOAD_OK: &str = r#"{"errcode":0,"errmsg":"ok","type":"file","media_id":"1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0","created_at":"1380000000"}"#;
+
+    #[test]
+    fn upload_retry_after_interrupt() {
+        let server = MockServer::start(vec![Reply::Drop, Reply::json(200, UPLOAD_OK)]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .max_retries(1)
+            .retry_backoff(Duration::from_millis(10))
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png",
+            )
+            .unwrap();
+        assert_eq!(
+            resp.media_id,
+            "1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0"
+        );
+
+        let reqs = server.requests();
+        assert_eq!(reqs.len(), 2);
+        for req in reqs {
+            assert!(req.path.starts_with("/upload_media?key="));
+            assert!(req.body_str().contains("tiny-rust-logo.png"));
+        }
+
+        let server = MockServer::start(vec![Reply::Drop]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            bot.upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png"
+            ),
+            Err(WeComError::Network { .. })
+        ));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn upload_retry_after_interrupt_async() {
+        let server = MockServer::start(vec![Reply::Drop, Reply::json(200, UPLOAD_OK)]);
+        let bot = super::WeComBotAsync::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .max_retries(2)
+            .retry_backoff(Duration::from_millis(10))
+            .build()
+            .unwrap();
+
+        let resp = bot
+            .upload(
+                crate::MediaType::File,
+                "./src/tests/imgs/tiny-rust-logo.png",
+            )
+            .await
+            .unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(server.requests().len(), 2);
+    }
+
     #[tokio::test]
     #[cfg(feature = "async_api")]
     async fn send_msg_async() {
@@ -0,0 +1,293 @@
+use std::any;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::media::MediaType;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WeComError {
+    #[error("wecom bot key not set")]
+    KeyNotFound,
+    #[error("wecom bot key is not a valid webhook key")]
+    InvalidKey,
+    #[cfg(feature = "client")]
+    #[error("network failed: {source}")]
+    Network {
+        #[from]
+        source: reqwest::Error,
+    },
+    #[cfg(feature = "client")]
+    #[error("wecom bot server error: {status}{}", body_suffix(.body))]
+    Http {
+        status: reqwest::StatusCode,
+        /// The response body, truncated if too long.
+        body: String,
+    },
+    #[error("could not parse {typename} data from JSON: {source}, body: {body}")]
+    DataType {
+        #[source]
+        source: serde_json::Error,
+        typename: &'static str,
+        /// The raw response body that failed to parse, truncated if too long.
+        body: String,
+    },
+    #[error("{kind}: {source}")]
+    Io { kind: IoKind, source: io::Error },
+    #[error("failed to decode base64 image: {source}")]
+    Base64Decode { source: base64::DecodeError },
+    #[error("unsupported image content type: {0}")]
+    ImageType(String),
+    #[error("unknown upload media type: {0}")]
+    MediaType(String),
+    #[error("news message requires 1 to 8 articles, got {actual}")]
+    InvalidArticleCount { actual: usize },
+    #[error("message content is {actual} bytes, exceeds the limit of {limit} bytes")]
+    ContentTooLong { limit: usize, actual: usize },
+    #[error("media_id is empty, check the errcode of the upload")]
+    EmptyMediaId,
+    #[error("article pic is {width}x{height}, far from the recommended ratio of 1068x455")]
+    PicAspectRatio { width: u32, height: u32 },
+    #[error("unknown message prefix {0:?}")]
+    UnknownMessagePrefix(String),
+    #[error("markdown message does not support {0} syntax")]
+    UnsupportedMarkdown(&'static str),
+    #[error("{field} is {actual} bytes, exceeds the limit of {limit} bytes")]
+    FieldTooLong {
+        field: &'static str,
+        limit: usize,
+        actual: usize,
+    },
+    #[error("article {field} is not a valid http(s) url: {value:?}")]
+    InvalidUrl { field: &'static str, value: String },
+    #[error("{0} can only be uploaded, it cannot be sent as a wecom bot message")]
+    NotSendable(MediaType),
+    #[error("wecom bot api error {err_code}: {err_msg}")]
+    Api { err_code: i64, err_msg: String },
+    /// The wecom bot API rejected the uploaded file with `errcode` 40009 for
+    /// exceeding the size limit of its media type.
+    #[error("uploaded media is too large: {err_msg}")]
+    MediaTooLarge { err_msg: String },
+    /// The wecom bot API rejected the message with `errcode` 45009 for
+    /// exceeding the frequency limit of 20 messages per minute.
+    #[error("wecom bot api rate limited{}", retry_after_suffix(.retry_after))]
+    RateLimited {
+        /// The wait parsed from the `Retry-After` header, if it was sent.
+        retry_after: Option<Duration>,
+    },
+}
+
+/// What the failed IO operation of `WeComError::Io` was for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IoKind {
+    /// Reading an image file for an image message.
+    Image,
+    /// Reading a local file to upload.
+    Upload,
+    /// Any other IO operation, e.g. converted by `?`.
+    Other,
+}
+
+impl fmt::Display for IoKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoKind::Image => f.write_str("failed to read image file"),
+            IoKind::Upload => f.write_str("failed to read upload file"),
+            IoKind::Other => f.write_str("io error"),
+        }
+    }
+}
+
+impl From<io::Error> for WeComError {
+    fn from(source: io::Error) -> Self {
+        WeComError::Io {
+            kind: IoKind::Other,
+            source,
+        }
+    }
+}
+
+impl WeComError {
+    #[cfg(feature = "client")]
+    pub(crate) fn network(source: reqwest::Error) -> Self {
+        WeComError::Network { source }
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn http(status: reqwest::StatusCode, body: &[u8]) -> Self {
+        WeComError::Http {
+            status,
+            body: truncate_body(body),
+        }
+    }
+
+    pub(crate) fn data_type<T>(source: serde_json::Error, body: &[u8]) -> Self {
+        WeComError::DataType {
+            source,
+            typename: any::type_name::<T>(),
+            body: truncate_body(body),
+        }
+    }
+
+    pub(crate) fn image(source: io::Error) -> Self {
+        WeComError::Io {
+            kind: IoKind::Image,
+            source,
+        }
+    }
+
+    pub(crate) fn base64_decode(source: base64::DecodeError) -> Self {
+        WeComError::Base64Decode { source }
+    }
+
+    pub(crate) fn load_file(source: io::Error) -> Self {
+        WeComError::Io {
+            kind: IoKind::Upload,
+            source,
+        }
+    }
+
+    /// Returns the HTTP status code if the error was caused by an unsuccessful
+    /// response status.
+    #[cfg(feature = "client")]
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            WeComError::Http { status, .. } => Some(*status),
+            WeComError::Network { source } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the error is from the underlying network.
+    #[cfg(feature = "client")]
+    pub fn is_network(&self) -> bool {
+        matches!(self, WeComError::Network { .. })
+    }
+
+    /// Returns the `errcode` if the error was reported by the wecom bot API.
+    pub fn api_code(&self) -> Option<i64> {
+        match self {
+            WeComError::Api { err_code, .. } => Some(*err_code),
+            WeComError::RateLimited { .. } => Some(API_RATE_LIMITED),
+            WeComError::MediaTooLarge { .. } => Some(API_MEDIA_TOO_LARGE),
+            _ => None,
+        }
+    }
+
+    /// Returns a clonable copy of the error, with the non-clonable sources
+    /// flattened into the rendered message, e.g. to broadcast it to multiple
+    /// consumers.
+    pub fn to_clonable(&self) -> ClonableWeComError {
+        #[cfg(feature = "client")]
+        let status = self.status().map(|s| s.as_u16());
+        #[cfg(not(feature = "client"))]
+        let status = None;
+
+        ClonableWeComError {
+            message: self.to_string(),
+            status,
+            api_code: self.api_code(),
+        }
+    }
+}
+
+/// A clonable snapshot of a `WeComError`, see `WeComError::to_clonable`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message}")]
+pub struct ClonableWeComError {
+    message: String,
+    status: Option<u16>,
+    api_code: Option<i64>,
+}
+
+impl ClonableWeComError {
+    /// Returns the rendered message of the original error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the HTTP status code of the original error, if any.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Returns the `errcode` of the original error, if any.
+    pub fn api_code(&self) -> Option<i64> {
+        self.api_code
+    }
+}
+
+impl From<&WeComError> for ClonableWeComError {
+    fn from(err: &WeComError) -> Self {
+        err.to_clonable()
+    }
+}
+
+pub(crate) type WeComResult<T> = Result<T, WeComError>;
+
+/// The `errcode` of the wecom bot API for exceeding the frequency limit.
+pub(crate) const API_RATE_LIMITED: i64 = 45009;
+
+/// The `errcode` of the wecom bot API for an uploaded file exceeding the
+/// size limit.
+pub(crate) const API_MEDIA_TOO_LARGE: i64 = 40009;
+
+fn retry_after_suffix(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(wait) => format!(", retry after {}s", wait.as_secs()),
+        None => String::new(),
+    }
+}
+
+/// The maximum length of response body kept in errors.
+pub(crate) const MAX_ERROR_BODY_LEN: usize = 512;
+
+fn body_suffix(body: &str) -> String {
+    if body.is_empty() {
+        String::new()
+    } else {
+        format!(" - {}", body)
+    }
+}
+
+/// Renders the response body as text for errors, invalid UTF-8 is replaced
+/// and long body is truncated at a char boundary.
+pub(crate) fn truncate_body(body: &[u8]) -> String {
+    let mut text = String::from_utf8_lossy(body).into_owned();
+    if text.len() > MAX_ERROR_BODY_LEN {
+        let mut end = MAX_ERROR_BODY_LEN;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("...");
+    }
+    text
+}
+
+#[cfg(test)]
+mod error_test {
+    use super::{ClonableWeComError, WeComError};
+
+    #[test]
+    fn to_clonable() {
+        let err = WeComError::Api {
+            err_code: 93000,
+            err_msg: String::from("invalid webhook url"),
+        };
+        let clonable = err.to_clonable();
+        let cloned = clonable.clone();
+        assert_eq!(cloned, clonable);
+        assert_eq!(cloned.to_string(), err.to_string());
+        assert_eq!(cloned.api_code(), Some(93000));
+        assert_eq!(cloned.status(), None);
+
+        let err = WeComError::from(std::io::Error::other("disk on fire"));
+        let clonable = ClonableWeComError::from(&err);
+        assert_eq!(clonable.message(), "io error: disk on fire");
+    }
+}
@@ -1,40 +1,227 @@
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::sync::OnceLock;
 
 use base64::{engine::general_purpose, Engine as _};
-use md5;
 
-use crate::bot::WeComError;
+use crate::error::WeComError;
 
 pub struct Image {
     content: Vec<u8>,
+
+    /// memoized base64 and md5 of `content`, computed on first `encode`.
+    encoded: OnceLock<(String, String)>,
 }
 
 impl Image {
     pub fn new(data: Vec<u8>) -> Self {
-        Self { content: data }
+        Self {
+            content: data,
+            encoded: OnceLock::new(),
+        }
     }
 
+    /// Reads the image from a local file.
+    ///
+    /// With the `image-convert` feature, a WEBP image is converted to PNG as
+    /// wecom only supports JPG and PNG.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, WeComError> {
         let mut file = File::open(path).map_err(WeComError::image)?;
         let mut buf: Vec<u8> = Vec::new();
         file.read_to_end(&mut buf).map_err(WeComError::image)?;
-        Ok(Self { content: buf })
+        #[cfg(feature = "image-convert")]
+        let buf = webp_to_png(buf)?;
+        Ok(Self::new(buf))
+    }
+
+    /// Same as `from_file` but reads the file in async, so that the executor
+    /// is not blocked.
+    #[cfg(feature = "async_api")]
+    pub async fn from_file_async<P: AsRef<Path>>(path: P) -> Result<Self, WeComError> {
+        let buf = tokio::fs::read(path).await.map_err(WeComError::image)?;
+        #[cfg(feature = "image-convert")]
+        let buf = webp_to_png(buf)?;
+        Ok(Self::new(buf))
+    }
+
+    /// Constructs an `Image` from base64 encoded data.
+    ///
+    /// A data URL prefix such as `data:image/png;base64,` is stripped if present.
+    pub fn from_base64(s: &str) -> Result<Self, WeComError> {
+        let data = match s.split_once(";base64,") {
+            Some((prefix, data)) if prefix.starts_with("data:") => data,
+            _ => s,
+        };
+        let buf = general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(WeComError::base64_decode)?;
+        Ok(Self::new(buf))
+    }
+
+    /// Fetches the image from `url`, only PNG and JPEG content are accepted.
+    ///
+    /// Use `WeComBot::client` to reuse the client of an existing bot.
+    #[cfg(feature = "client")]
+    pub fn from_url(client: &reqwest::blocking::Client, url: &str) -> Result<Self, WeComError> {
+        let resp = client.get(url).send()?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(WeComError::http(status, &resp.bytes().unwrap_or_default()));
+        }
+        check_content_type(resp.headers())?;
+
+        Ok(Self::new(resp.bytes()?.to_vec()))
+    }
+
+    /// Fetches the image from `url` in async, only PNG and JPEG content are
+    /// accepted.
+    #[cfg(feature = "async_api")]
+    pub async fn from_url_async(client: &reqwest::Client, url: &str) -> Result<Self, WeComError> {
+        let resp = client.get(url).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(WeComError::http(
+                status,
+                &resp.bytes().await.unwrap_or_default(),
+            ));
+        }
+        check_content_type(resp.headers())?;
+
+        Ok(Self::new(resp.bytes().await?.to_vec()))
+    }
+
+    /// Returns the width and height of the image in pixels, parsed from the
+    /// PNG `IHDR` chunk or the JPEG `SOF` marker without decoding the image.
+    ///
+    /// Returns `None` if the image is neither PNG nor JPEG or is malformed.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        png_dimensions(&self.content).or_else(|| jpeg_dimensions(&self.content))
+    }
+
+    /// Returns the hex encoded MD5 of the image data, as sent in an image
+    /// message.
+    pub fn md5(&self) -> String {
+        self.encode().1
+    }
+
+    /// Returns the base64 encoded image data, as sent in an image message.
+    pub fn base64(&self) -> String {
+        self.encode().0
     }
 
     /// return encoded base64 and md5 of image data
+    ///
+    /// The encoding is computed once and cached, repeated calls are cheap.
     pub(crate) fn encode(&self) -> (String, String) {
-        let b64 = general_purpose::STANDARD.encode(self.content.clone());
-        let m5 = md5::compute(self.content.clone());
+        self.encoded
+            .get_or_init(|| {
+                let b64 = general_purpose::STANDARD.encode(&self.content);
+                let m5 = md5::compute(&self.content);
+                (b64, format!("{:x}", m5))
+            })
+            .clone()
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
 
-        (b64, format!("{:x}", m5))
+/// Re-encodes a WEBP image as PNG, which is supported by wecom, other data
+/// is returned unchanged.
+#[cfg(feature = "image-convert")]
+fn webp_to_png(data: Vec<u8>) -> Result<Vec<u8>, WeComError> {
+    // "RIFF" + file size(4) + "WEBP"
+    if !(data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP")) {
+        return Ok(data);
+    }
+    let unsupported = |_| WeComError::ImageType(String::from("image/webp"));
+    let img = ::image::load_from_memory_with_format(&data, ::image::ImageFormat::WebP)
+        .map_err(unsupported)?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, ::image::ImageFormat::Png)
+        .map_err(unsupported)?;
+    Ok(png.into_inner())
+}
+
+fn png_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    // signature(8) + chunk length(4) + "IHDR"(4) + width(4) + height(4)
+    if !data.starts_with(PNG_SIGNATURE) || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        pos += 2;
+        match marker {
+            // fill bytes before a marker
+            0xFF => pos -= 1,
+            // standalone markers without segment length
+            0x01 | 0xD0..=0xD7 => {}
+            // SOFn markers, except DHT, JPG and DAC which share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                // length(2) + precision(1) + height(2) + width(2)
+                let height = u16::from_be_bytes(data.get(pos + 3..pos + 5)?.try_into().ok()?);
+                let width = u16::from_be_bytes(data.get(pos + 5..pos + 7)?.try_into().ok()?);
+                return Some((width as u32, height as u32));
+            }
+            // start of scan or end of image before any frame header
+            0xDA | 0xD9 => return None,
+            _ => {
+                let len = u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?);
+                pos += len as usize;
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Image {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
+}
+
+impl TryFrom<&Path> for Image {
+    type Error = WeComError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::from_file(path)
+    }
+}
+
+#[cfg(feature = "client")]
+fn check_content_type(headers: &reqwest::header::HeaderMap) -> Result<(), WeComError> {
+    let content_type = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+
+    match mime.to_lowercase().as_str() {
+        "image/png" | "image/jpeg" => Ok(()),
+        _ => Err(WeComError::ImageType(content_type.to_string())),
     }
 }
 
 #[cfg(test)]
 mod image_test {
+    use std::path::Path;
+
     use super::Image;
+    #[cfg(feature = "client")]
+    use crate::tests::{MockServer, Reply};
 
     #[test]
     pub fn encode() {
@@ -43,4 +230,136 @@ mod image_test {
         assert_eq!(img.encode().0, "iVBORw0KGgoAAAANSUhEUgAAAAoAAAAKCAYAAACNMs+9AAAAAXNSR0IArs4c6QAAAJFJREFUKFON0DEOgUEUBODvl6CQqCV6Bc6hopdotO7gHHqNC3ANrcQBJFqNRIO8ZFf+bEJs87Iz82ZepvLnqwpdAy0cMapzdeErERmL/xbLwDMY81m4X9DPmixs4oAJrujhjAHinI/jCUNEXCzf0E0JHdzLG2eJDPcp9mV08BusMMcYayywq0eXbbbx+FbPz+rfkJoUC+KW8YsAAAAASUVORK5CYII=");
         assert_eq!(img.encode().1, "4d1b24690a324e7ac911c3c721982951");
     }
+
+    #[test]
+    pub fn from_base64() {
+        let data = "iVBORw0KGgoAAAANSUhEUgAAAAoAAAAKCAYAAACNMs+9AAAAAXNSR0IArs4c6QAAAJFJREFUKFON0DEOgUEUBODvl6CQqCV6Bc6hopdotO7gHHqNC3ANrcQBJFqNRIO8ZFf+bEJs87Iz82ZepvLnqwpdAy0cMapzdeErERmL/xbLwDMY81m4X9DPmixs4oAJrujhjAHinI/jCUNEXCzf0E0JHdzLG2eJDPcp9mV08BusMMcYayywq0eXbbbx+FbPz+rfkJoUC+KW8YsAAAAASUVORK5CYII=";
+
+        let img = Image::from_base64(&format!("data:image/png;base64,{}", data)).unwrap();
+        assert_eq!(img.encode().0, data);
+        assert_eq!(img.encode().1, "4d1b24690a324e7ac911c3c721982951");
+
+        let img = Image::from_base64(data).unwrap();
+        assert_eq!(img.encode().1, "4d1b24690a324e7ac911c3c721982951");
+
+        assert!(matches!(
+            Image::from_base64("not base64!"),
+            Err(crate::WeComError::Base64Decode { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn from_file_async() {
+        let img = Image::from_file_async("src/tests/imgs/tiny-rust-logo.png")
+            .await
+            .unwrap();
+        let sync = Image::from_file("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        assert_eq!(img.encode(), sync.encode());
+
+        assert!(matches!(
+            Image::from_file_async("src/tests/imgs/missing.png").await,
+            Err(crate::WeComError::Io {
+                kind: crate::IoKind::Image,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    pub fn conversions() {
+        let logo = std::fs::read("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        let img = Image::from(logo.clone());
+        assert_eq!(img.content, logo);
+
+        let img = Image::try_from(Path::new("src/tests/imgs/tiny-rust-logo.png")).unwrap();
+        assert_eq!(img.content, logo);
+
+        assert!(matches!(
+            Image::try_from(Path::new("src/tests/imgs/missing.png")),
+            Err(crate::WeComError::Io { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "client")]
+    pub fn from_url() {
+        let logo = std::fs::read("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        let server = MockServer::start(vec![
+            Reply::bytes(200, "image/png", logo.clone()),
+            Reply::bytes(200, "text/html; charset=utf-8", b"<html></html>".to_vec()),
+        ]);
+        let client = reqwest::blocking::Client::new();
+        let url = format!("{}/logo.png", server.url());
+
+        let img = Image::from_url(&client, &url).unwrap();
+        assert_eq!(img.content, logo);
+        assert_eq!(img.encode().1, "4d1b24690a324e7ac911c3c721982951");
+
+        assert!(matches!(
+            Image::from_url(&client, &url),
+            Err(crate::WeComError::ImageType(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async_api")]
+    async fn from_url_async() {
+        let logo = std::fs::read("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        let server = MockServer::start(vec![Reply::bytes(200, "image/png", logo.clone())]);
+        let url = format!("{}/logo.png", server.url());
+
+        let img = Image::from_url_async(&reqwest::Client::new(), &url)
+            .await
+            .unwrap();
+        assert_eq!(img.content, logo);
+    }
+
+    #[test]
+    pub fn dimensions() {
+        let img = Image::from_file("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        assert_eq!(img.dimensions(), Some((10, 10)));
+
+        let img = Image::from_file("src/tests/imgs/profile.png").unwrap();
+        assert_eq!(img.dimensions(), Some((533, 700)));
+
+        // SOI, APP0 with 2 bytes payload, SOF0 of 1068x455
+        let jpeg = vec![
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01,
+            0xC7, 0x04, 0x2C, 0x03,
+        ];
+        assert_eq!(Image::new(jpeg).dimensions(), Some((1068, 455)));
+
+        assert_eq!(Image::new(b"GIF89a".to_vec()).dimensions(), None);
+        assert_eq!(Image::new(vec![0xFF, 0xD8, 0xFF]).dimensions(), None);
+    }
+
+    #[test]
+    pub fn encode_cached() {
+        let img = Image::new(b"image".to_vec());
+
+        let first = img.encode();
+        assert_eq!(first, img.encode());
+        assert_eq!(first.0, img.encoded.get().unwrap().0);
+    }
+
+    #[test]
+    pub fn accessors() {
+        let img = Image::from_file("src/tests/imgs/tiny-rust-logo.png").unwrap();
+
+        assert_eq!(img.md5(), "4d1b24690a324e7ac911c3c721982951");
+        assert_eq!(img.base64(), img.encode().0);
+    }
+
+    #[cfg(feature = "image-convert")]
+    #[test]
+    pub fn from_webp_file() {
+        let img = Image::from_file("src/tests/imgs/tiny-rust-logo.webp").unwrap();
+        assert!(img.content.starts_with(super::PNG_SIGNATURE));
+        assert_eq!(img.dimensions(), Some((10, 10)));
+
+        let png = Image::from_file("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        let pixels = |img: &Image| image::load_from_memory(&img.content).unwrap().to_rgba8();
+        assert_eq!(pixels(&img), pixels(&png));
+    }
 }
@@ -16,17 +16,53 @@
 //! }
 //! ```
 
+#[cfg(feature = "client")]
 mod bot;
+mod error;
 mod image;
 mod media;
 mod message;
 mod response;
+#[cfg(feature = "client")]
+mod worker;
 
-pub use bot::{WeComBot, WeComBotBuilder, WeComError};
+#[cfg(all(test, feature = "client"))]
+mod tests;
+
+#[cfg(feature = "client")]
+pub use bot::{SendMeta, WeComBot, WeComBotBuilder};
+pub use error::{ClonableWeComError, IoKind, WeComError};
 pub use image::Image;
 pub use media::MediaType;
-pub use message::{Article, Message};
+pub use message::{markdown, Article, MarkdownBuilder, MarkdownSection, Message};
 pub use response::{SendResp, UploadResp};
+#[cfg(feature = "client")]
+pub use worker::WeComBotWorker;
 
 #[cfg(feature = "async_api")]
 pub use bot::{WeComBotAsync, WeComBotAsyncBuilder};
+
+/// Re-exports of the commonly used types, `use wecom_bot::prelude::*;` to
+/// import them all at once.
+///
+/// ```
+/// use wecom_bot::prelude::*;
+///
+/// fn main() -> Result<(), WeComError> {
+///     let bot = WeComBot::builder()
+///         .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+///         .dry_run(true)
+///         .build()?;
+///     let rsp: SendResp = bot.send(Message::news(vec![Article::new("title", "https://www.qq.com")]))?;
+///     assert!(rsp.is_ok());
+///
+///     Ok(())
+/// }
+/// ```
+pub mod prelude {
+    pub use crate::{Article, Image, MediaType, Message, SendResp, UploadResp, WeComError};
+    #[cfg(feature = "client")]
+    pub use crate::{WeComBot, WeComBotBuilder};
+    #[cfg(feature = "async_api")]
+    pub use crate::{WeComBotAsync, WeComBotAsyncBuilder};
+}
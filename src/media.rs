@@ -1,7 +1,12 @@
+use std::fmt;
 use std::str::FromStr;
 
-use crate::WeComError;
+use serde::{Deserialize, Serialize};
 
+use crate::error::WeComError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MediaType {
     File,
     Image,
@@ -23,22 +28,75 @@ impl FromStr for MediaType {
     }
 }
 
-impl ToString for MediaType {
-    fn to_string(&self) -> String {
+impl AsRef<str> for MediaType {
+    fn as_ref(&self) -> &'static str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl MediaType {
+    /// Returns the `type` name of the media in the wecom bot API.
+    pub const fn as_str(&self) -> &'static str {
         match *self {
-            MediaType::File => String::from("file"),
-            MediaType::Image => String::from("image"),
-            MediaType::Voice => String::from("voice"),
-            MediaType::Video => String::from("video"),
+            MediaType::File => "file",
+            MediaType::Image => "image",
+            MediaType::Voice => "voice",
+            MediaType::Video => "video",
         }
     }
+
+    pub(crate) fn format_upload_url(&self, base: &str) -> String {
+        format!("{}&type={}", base, self.as_str())
+    }
 }
 
-impl MediaType {
-    pub(crate) fn format_upload_url<U>(&self, base: U) -> String
-    where
-        U: Into<String>,
-    {
-        format!("{}&type={}", base.into(), self.to_string())
+#[cfg(test)]
+mod media_test {
+    use super::MediaType;
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", MediaType::Voice), "voice");
+        assert_eq!(MediaType::Image.to_string(), "image");
+        assert_eq!(MediaType::File.as_ref(), "file");
+        assert_eq!(
+            MediaType::Video.format_upload_url("https://example.com/upload_media?key=k"),
+            "https://example.com/upload_media?key=k&type=video"
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let voice: MediaType = serde_json::from_str(r#""voice""#).unwrap();
+        assert!(matches!(voice, MediaType::Voice));
+        assert_eq!(serde_json::to_string(&voice).unwrap(), r#""voice""#);
+
+        assert!(serde_json::from_str::<MediaType>(r#""gif""#).is_err());
+    }
+
+    #[test]
+    fn as_str_round_trip() {
+        for media_type in [
+            MediaType::File,
+            MediaType::Image,
+            MediaType::Voice,
+            MediaType::Video,
+        ] {
+            let name = media_type.as_str();
+            assert_eq!(name, media_type.to_string());
+            assert_eq!(name.parse::<MediaType>().unwrap().as_str(), name);
+        }
+    }
+
+    #[test]
+    fn eq() {
+        assert_eq!(MediaType::Image, MediaType::Image);
+        assert_ne!(MediaType::Image, MediaType::Video);
     }
 }
@@ -1,16 +1,38 @@
 use std::borrow::Cow;
+use std::path::Path;
+use std::str::FromStr;
 
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::error::{WeComError, WeComResult};
 use crate::image::Image;
 
 static GROUP_REBOT_MSG_TEXT: &str = "text";
 static GROUP_REBOT_MSG_MARKDOWN: &str = "markdown";
+static GROUP_REBOT_MSG_MARKDOWN_V2: &str = "markdown_v2";
 static GROUP_REBOT_MSG_IMAGE: &str = "image";
 static GROUP_REBOT_MSG_NEWS: &str = "news";
 static GROUP_REBOT_MSG_FILE: &str = "file";
+static GROUP_REBOT_MSG_VOICE: &str = "voice";
+/// Not a wecom msgtype, marks a message built by `Message::raw`.
+static GROUP_REBOT_MSG_RAW: &str = "raw";
 
-#[derive(Debug, Clone, Serialize)]
+const NEWS_MAX_ARTICLES: usize = 8;
+pub(crate) const TEXT_MAX_BYTES: usize = 2048;
+/// The special id that mentions everyone in the group.
+const MENTION_ALL: &str = "@all";
+pub(crate) const MARKDOWN_MAX_BYTES: usize = 4096;
+const ARTICLE_TITLE_MAX_BYTES: usize = 128;
+const ARTICLE_DESC_MAX_BYTES: usize = 512;
+const IMAGE_MAX_BYTES: usize = 2 * 1024 * 1024;
+
+/// The recommended size of the large image of a news article.
+const PIC_LARGE_WIDTH: u32 = 1068;
+const PIC_LARGE_HEIGHT: u32 = 455;
+/// How far the aspect ratio of an article image may be off the recommended.
+const PIC_RATIO_TOLERANCE: f64 = 0.3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 enum MessageBody<'a> {
     #[serde(rename = "text")]
     Text {
@@ -35,6 +57,11 @@ enum MessageBody<'a> {
         /// markdown raw text content, up to 4096 bytes.
         content: Cow<'a, str>,
     },
+    #[serde(rename = "markdown_v2")]
+    MarkdownV2 {
+        /// markdown_v2 raw text content, up to 4096 bytes.
+        content: Cow<'a, str>,
+    },
     #[serde(rename = "image")]
     Image {
         /// base64 encoding of image content.
@@ -53,6 +80,15 @@ enum MessageBody<'a> {
         /// File id, obtained through the wecom bot upload interface mentioned.
         media_id: Cow<'a, str>,
     },
+    #[serde(rename = "voice")]
+    Voice {
+        /// Voice file id, obtained through the wecom bot upload interface
+        /// mentioned.
+        media_id: Cow<'a, str>,
+    },
+    /// A pre-built JSON payload sent verbatim, including its `msgtype`.
+    #[serde(skip)]
+    Raw(serde_json::Value),
 }
 
 macro_rules! inject_iter_fields {
@@ -62,28 +98,129 @@ macro_rules! inject_iter_fields {
             I: IntoIterator<Item = S>,
             S: Into<Cow<'a, str>>,
         {
-            match &mut self.body {
-                $matched_type { $field_name, .. } => {
-                    let vs: Vec<Cow<'a, str>> = iter.into_iter().map(Into::into).collect();
-                    *$field_name = Some(vs);
-                    self
-                }
-                _ => self,
+            if let $matched_type { $field_name, .. } = &mut self.body {
+                let vs: Vec<Cow<'a, str>> = iter.into_iter().map(Into::into).collect();
+                *$field_name = Some(vs);
+                self.dedup_mentions();
+            }
+            self
+        }
+    };
+}
+
+macro_rules! push_iter_fields {
+    ($fn_name:ident, $field_name:tt, $matched_type:path) => {
+        pub fn $fn_name<S>(mut self, one: S) -> Self
+        where
+            S: Into<Cow<'a, str>>,
+        {
+            if let $matched_type { $field_name, .. } = &mut self.body {
+                $field_name.get_or_insert_with(Vec::new).push(one.into());
+                self.dedup_mentions();
             }
+            self
         }
     };
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// A wecom bot message, built by one of the `Message::*` constructors.
+///
+/// Messages do nothing on their own, dropping one without sending it is
+/// reported by the compiler:
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// wecom_bot::Message::text("forgot to send");
+/// ```
+#[must_use = "messages do nothing unless sent"]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message<'a> {
     /// Type of message.
-    #[serde(rename = "msgtype")]
     msg_type: &'static str,
 
-    #[serde(flatten)]
     body: MessageBody<'a>,
 }
 
+impl<'a> MessageBody<'a> {
+    /// Returns the `msgtype` the body is sent with.
+    fn msg_type(&self) -> &'static str {
+        match self {
+            MessageBody::Text { .. } => GROUP_REBOT_MSG_TEXT,
+            MessageBody::Markdown { .. } => GROUP_REBOT_MSG_MARKDOWN,
+            MessageBody::MarkdownV2 { .. } => GROUP_REBOT_MSG_MARKDOWN_V2,
+            MessageBody::Image { .. } => GROUP_REBOT_MSG_IMAGE,
+            MessageBody::News { .. } => GROUP_REBOT_MSG_NEWS,
+            MessageBody::File { .. } => GROUP_REBOT_MSG_FILE,
+            MessageBody::Voice { .. } => GROUP_REBOT_MSG_VOICE,
+            MessageBody::Raw(_) => GROUP_REBOT_MSG_RAW,
+        }
+    }
+}
+
+/// The JSON payload of a message other than `Message::raw`.
+#[derive(Serialize, Deserialize)]
+struct Payload<M, B> {
+    msgtype: M,
+    #[serde(flatten)]
+    body: B,
+}
+
+impl<'a> Serialize for Message<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.body {
+            MessageBody::Raw(value) => value.serialize(serializer),
+            body => Payload {
+                msgtype: self.msg_type,
+                body,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+/// Reads back a message from its JSON payload, e.g. one persisted to a
+/// durable outbox. The `msgtype` must match the body of the message, and a
+/// `msgtype` unknown to the crate is read as a `Message::raw`.
+impl<'de, 'a> Deserialize<'de> for Message<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const KNOWN: [&str; 7] = [
+            GROUP_REBOT_MSG_TEXT,
+            GROUP_REBOT_MSG_MARKDOWN,
+            GROUP_REBOT_MSG_MARKDOWN_V2,
+            GROUP_REBOT_MSG_IMAGE,
+            GROUP_REBOT_MSG_NEWS,
+            GROUP_REBOT_MSG_FILE,
+            GROUP_REBOT_MSG_VOICE,
+        ];
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match value.get("msgtype").and_then(|t| t.as_str()) {
+            Some(msg_type) if KNOWN.contains(&msg_type) => {}
+            _ => return Ok(Message::raw(value)),
+        }
+
+        let payload = Payload::<String, MessageBody<'a>>::deserialize(value)
+            .map_err(serde::de::Error::custom)?;
+        let msg_type = payload.body.msg_type();
+        if payload.msgtype != msg_type {
+            return Err(serde::de::Error::custom(format!(
+                "msgtype {:?} does not match the {:?} body",
+                payload.msgtype, msg_type
+            )));
+        }
+        Ok(Message {
+            msg_type,
+            body: payload.body,
+        })
+    }
+}
+
 impl<'a> Message<'a> {
     /// Returns a raw text wecom `Message` without mentioned anyone.
     ///
@@ -99,7 +236,7 @@ impl<'a> Message<'a> {
     ///
     /// # fn main() -> Result<(), WeComError> {
     ///     let msg = Message::text("hello world!");
-    ///     let _rsp: SendResp = WeComBot::builder().key("xxx").build()?.send(msg)?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
     /// #   Ok(())
     /// # }
     /// ```
@@ -117,6 +254,63 @@ impl<'a> Message<'a> {
         }
     }
 
+    /// Returns a text wecom `Message` of the `lines` joined with `\n`.
+    ///
+    /// Fails if the joined content exceeds 2048 bytes.
+    ///
+    /// ```
+    /// # use wecom_bot::Message;
+    ///
+    /// let msg = Message::text_lines(["deploy finished", "env: prod", "took: 42s"]).unwrap();
+    /// ```
+    pub fn text_lines<I, S>(lines: I) -> WeComResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::text_lines_with_limit(lines, TEXT_MAX_BYTES)
+    }
+
+    /// Same as `text_lines` but fails if the joined content exceeds
+    /// `max_bytes`, e.g. the `max_text_bytes` set on the bot builder.
+    pub fn text_lines_with_limit<I, S>(lines: I, max_bytes: usize) -> WeComResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let content = lines
+            .into_iter()
+            .map(|line| line.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        check_content_len(&content, max_bytes)?;
+        Ok(Self::text(content))
+    }
+
+    /// Splits `content` into text `Message`s of no more than 2048 bytes each,
+    /// so that oversized text can be sent as multiple messages in order.
+    ///
+    /// Chunks are split at the last newline that fits, which is dropped, or at
+    /// a char boundary if a line is too long by itself.
+    pub fn text_chunks(content: &'a str) -> Vec<Self> {
+        let mut chunks = Vec::new();
+        let mut rest = content;
+        while rest.len() > TEXT_MAX_BYTES {
+            let mut end = TEXT_MAX_BYTES;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (chunk, next) = match rest[..end].rfind('\n') {
+                Some(i) if i > 0 => (&rest[..i], &rest[i + 1..]),
+                _ => rest.split_at(end),
+            };
+            chunks.push(Self::text(chunk));
+            rest = next;
+        }
+        chunks.push(Self::text(rest));
+        chunks
+    }
+
     /// Returns a markdown syntax wecom `Message` that display rendered format.
     ///
     /// The maxium length of content up to 4096 bytes.
@@ -130,7 +324,7 @@ impl<'a> Message<'a> {
     ///
     /// # fn main() -> Result<(), WeComError> {
     ///     let msg = Message::markdown(r#"# hello world! <@1001>"#);
-    ///     let _rsp: SendResp = WeComBot::builder().key("xxx").build()?.send(msg)?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
     /// #   Ok(())
     /// # }
     /// ```
@@ -146,6 +340,83 @@ impl<'a> Message<'a> {
         }
     }
 
+    /// Returns a markdown wecom `Message` mentioning each of `user_ids` by the
+    /// `<@userid>` syntax, appended on their own line after `content`.
+    ///
+    /// ```
+    /// # use wecom_bot::Message;
+    ///
+    /// let msg = Message::markdown_mention("# Deploy failed", ["zhangsan", "lisi"]);
+    /// ```
+    pub fn markdown_mention<S, I, U>(content: S, user_ids: I) -> Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = U>,
+        U: AsRef<str>,
+    {
+        let mut content = content.into();
+        let mentions = user_ids
+            .into_iter()
+            .map(|id| format!("<@{}>", id.as_ref()))
+            .collect::<Vec<_>>();
+        if !mentions.is_empty() {
+            content.push('\n');
+            content.push_str(&mentions.join(" "));
+        }
+        Self::markdown(content)
+    }
+
+    /// Checks `content` for markdown syntax that the `markdown` message does
+    /// not render, such as images `![alt](url)` and tables, which may end up
+    /// as a blank message.
+    ///
+    /// This is an explicit helper, `markdown` does not validate its content.
+    ///
+    /// ```
+    /// # use wecom_bot::{Message, WeComError};
+    ///
+    /// assert!(Message::validate_markdown("# Deploy finished").is_ok());
+    /// assert!(matches!(
+    ///     Message::validate_markdown("![logo](https://www.qq.com/logo.png)"),
+    ///     Err(WeComError::UnsupportedMarkdown(_))
+    /// ));
+    /// ```
+    pub fn validate_markdown(content: &str) -> WeComResult<()> {
+        if let Some(start) = content.find("![") {
+            if content[start..].contains("](") {
+                return Err(WeComError::UnsupportedMarkdown("image"));
+            }
+        }
+        let is_table_delimiter = |line: &str| {
+            let line = line.trim();
+            line.contains('|')
+                && line.contains("---")
+                && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+        };
+        if content.lines().any(is_table_delimiter) {
+            return Err(WeComError::UnsupportedMarkdown("table"));
+        }
+        Ok(())
+    }
+
+    /// Returns a `markdown_v2` wecom `Message`, rendered by the newer markdown
+    /// engine of wecom which supports tables, lists and code blocks.
+    ///
+    /// The maxium length of content up to 4096 bytes, same as `markdown`.
+    ///
+    /// <https://developer.work.weixin.qq.com/document/path/91770#markdown-v2%E7%B1%BB%E5%9E%8B>
+    pub fn markdown_v2<S>(content: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        Self {
+            msg_type: GROUP_REBOT_MSG_MARKDOWN_V2,
+            body: MessageBody::MarkdownV2 {
+                content: content.into(),
+            },
+        }
+    }
+
     /// Returns a wecom `Message` that displays an image.
     ///
     /// The image format only supports JPG or PNG, with maximum size up to 2Mb.
@@ -157,7 +428,7 @@ impl<'a> Message<'a> {
     ///
     /// # fn main() -> Result<(), WeComError> {
     ///     let msg = Message::image(Image::from_file("src/tests/imgs/profile.png")?);
-    ///     let _rsp: SendResp = WeComBot::builder().key("xxx").build()?.send(msg)?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
     /// #   Ok(())
     /// # }
     /// ```
@@ -172,6 +443,30 @@ impl<'a> Message<'a> {
         }
     }
 
+    /// Returns an image wecom `Message` read from the file at `path`.
+    ///
+    /// Shortcut for `Message::image(Image::from_file(path)?)`.
+    ///
+    /// ```no_run
+    /// # use wecom_bot::{Message, SendResp, WeComBot, WeComError};
+    ///
+    /// # fn main() -> Result<(), WeComError> {
+    ///     let msg = Message::image_from_file("src/tests/imgs/profile.png")?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn image_from_file<P: AsRef<Path>>(path: P) -> WeComResult<Self> {
+        Ok(Self::image(Image::from_file(path)?))
+    }
+
+    /// Returns an image wecom `Message` of the raw PNG or JPG `bytes`.
+    ///
+    /// Shortcut for `Message::image(Image::new(bytes))`.
+    pub fn image_from_bytes(bytes: Vec<u8>) -> Self {
+        Self::image(Image::new(bytes))
+    }
+
     /// Returns an article wecom `Message` that can click then redirect to a new
     /// url in internal web brower.
     ///
@@ -185,7 +480,7 @@ impl<'a> Message<'a> {
     ///
     /// # fn main() -> Result<(), WeComError> {
     ///     let msg = Message::news(vec![Article::new("title", "url")]);
-    ///     let _rsp: SendResp = WeComBot::builder().key("xxx").build()?.send(msg)?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
     /// #   Ok(())
     /// # }
     /// ```
@@ -196,6 +491,29 @@ impl<'a> Message<'a> {
         }
     }
 
+    /// Same as `news` but checks the number of `articles` is between 1 and 8,
+    /// and each of them passes `Article::validate`.
+    ///
+    /// ```
+    /// # use wecom_bot::{Article, Message, WeComError};
+    ///
+    /// assert!(matches!(
+    ///     Message::try_news(vec![]),
+    ///     Err(WeComError::InvalidArticleCount { actual: 0 })
+    /// ));
+    /// ```
+    pub fn try_news(articles: Vec<Article<'a>>) -> WeComResult<Self> {
+        if articles.is_empty() || articles.len() > NEWS_MAX_ARTICLES {
+            return Err(WeComError::InvalidArticleCount {
+                actual: articles.len(),
+            });
+        }
+        for article in &articles {
+            article.validate()?;
+        }
+        Ok(Self::news(articles))
+    }
+
     /// Returns a file wecom `Message` that displays with its name and can be
     /// download.
     ///
@@ -208,7 +526,7 @@ impl<'a> Message<'a> {
     ///
     /// # fn main() -> Result<(), WeComError> {
     ///     let msg = Message::file("3a8asd892asd8asd");
-    ///     let _rsp: SendResp = WeComBot::builder().key("xxx").build()?.send(msg)?;
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
     /// #   Ok(())
     /// # }
     /// ```
@@ -224,85 +542,677 @@ impl<'a> Message<'a> {
         }
     }
 
+    /// Same as `file` but rejects a blank `media_id` with
+    /// `WeComError::EmptyMediaId`, which usually comes from a failed upload
+    /// whose `errcode` was not checked.
+    pub fn try_file<S>(media_id: S) -> WeComResult<Self>
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        let media_id = media_id.into();
+        if media_id.trim().is_empty() {
+            return Err(WeComError::EmptyMediaId);
+        }
+        Ok(Self::file(media_id))
+    }
+
+    /// Returns a voice wecom `Message` that can be played in the group.
+    ///
+    /// The required field `media_id` are obtained through the `upload` API
+    /// returned with `MediaType::Voice`, the voice file only supports AMR
+    /// format, with maximum size up to 2Mb and duration up to 60s.
+    ///
+    /// <https://developer.work.weixin.qq.com/document/path/91770#%E8%AF%AD%E9%9F%B3%E7%B1%BB%E5%9E%8B>
+    ///
+    /// ```
+    /// # use wecom_bot::{Message, SendResp, WeComBot, WeComError};
+    ///
+    /// # fn main() -> Result<(), WeComError> {
+    ///     let msg = Message::voice("3a8asd892asd8asd");
+    ///     let _rsp: SendResp = WeComBot::builder().key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa").build()?.send(msg)?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn voice<S>(media_id: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        Self {
+            msg_type: GROUP_REBOT_MSG_VOICE,
+            body: MessageBody::Voice {
+                media_id: media_id.into(),
+            },
+        }
+    }
+
     inject_iter_fields!(mentioned_list, MessageBody::Text);
 
     inject_iter_fields!(mentioned_mobile_list, MessageBody::Text);
+
+    push_iter_fields!(add_mentioned, mentioned_list, MessageBody::Text);
+
+    push_iter_fields!(
+        add_mentioned_mobile,
+        mentioned_mobile_list,
+        MessageBody::Text
+    );
+
+    /// Drops repeated ids within each mention list, keeping the first seen
+    /// order, and keeps `@all` in at most one list so that the group is not
+    /// notified twice.
+    fn dedup_mentions(&mut self) {
+        fn dedup(list: &mut Vec<Cow<'_, str>>) {
+            let mut seen = std::collections::HashSet::new();
+            list.retain(|id| seen.insert(id.clone()));
+        }
+
+        if let MessageBody::Text {
+            mentioned_list,
+            mentioned_mobile_list,
+            ..
+        } = &mut self.body
+        {
+            let mentioned_all = mentioned_list.as_mut().is_some_and(|list| {
+                dedup(list);
+                list.iter().any(|id| id == MENTION_ALL)
+            });
+            if let Some(list) = mentioned_mobile_list {
+                dedup(list);
+                if mentioned_all {
+                    list.retain(|id| id != MENTION_ALL);
+                }
+            }
+        }
+    }
+
+    /// Mentions everyone in the group, replacing any `mentioned_list` set before.
+    ///
+    /// Only takes effect on text message.
+    pub fn mention_all(self) -> Self {
+        self.mentioned_list([MENTION_ALL])
+    }
+
+    /// Returns a message sending the pre-built JSON `value` verbatim, e.g. to
+    /// use a message type of wecom the crate does not model yet.
+    ///
+    /// The `value` must carry the `msgtype` itself, it is not checked by the
+    /// crate.
+    ///
+    /// ```
+    /// # use wecom_bot::Message;
+    /// # use serde_json::json;
+    ///
+    /// let msg = Message::raw(json!({"msgtype": "text", "text": {"content": "hello"}}));
+    /// assert_eq!(msg.to_json().unwrap(), r#"{"msgtype":"text","text":{"content":"hello"}}"#);
+    /// ```
+    pub fn raw(value: serde_json::Value) -> Self {
+        Self {
+            msg_type: GROUP_REBOT_MSG_RAW,
+            body: MessageBody::Raw(value),
+        }
+    }
+
+    /// Returns the `msgtype` of the message.
+    pub(crate) fn msg_type(&self) -> &'static str {
+        self.msg_type
+    }
+
+    /// Checks the message against the limits of wecom bot API for its type,
+    /// and returns the first violation found:
+    ///
+    /// - text content up to 2048 bytes, markdown content up to 4096 bytes.
+    /// - 1 to 8 news articles, each passes `Article::validate` with a title
+    ///   up to 128 bytes and a description up to 512 bytes.
+    /// - image up to 2MB.
+    /// - file and voice with a non-blank `media_id`.
+    ///
+    /// ```
+    /// # use wecom_bot::{Message, WeComError};
+    ///
+    /// assert!(Message::text("hello world!").validate().is_ok());
+    /// assert!(matches!(
+    ///     Message::text("x".repeat(2049)).validate(),
+    ///     Err(WeComError::ContentTooLong { limit: 2048, actual: 2049 })
+    /// ));
+    /// ```
+    pub fn validate(&self) -> WeComResult<()> {
+        self.validate_with_limits(TEXT_MAX_BYTES, MARKDOWN_MAX_BYTES)
+    }
+
+    /// Same as `validate` but with the given limits of the content of text and
+    /// markdown messages, e.g. the `max_text_bytes` and `max_markdown_bytes`
+    /// set on the bot builder.
+    ///
+    /// ```
+    /// # use wecom_bot::Message;
+    ///
+    /// let msg = Message::text("x".repeat(3000));
+    /// assert!(msg.validate().is_err());
+    /// assert!(msg.validate_with_limits(4096, 4096).is_ok());
+    /// ```
+    pub fn validate_with_limits(
+        &self,
+        max_text_bytes: usize,
+        max_markdown_bytes: usize,
+    ) -> WeComResult<()> {
+        match &self.body {
+            MessageBody::Text { .. }
+            | MessageBody::Markdown { .. }
+            | MessageBody::MarkdownV2 { .. } => {
+                self.check_content_limits(max_text_bytes, max_markdown_bytes)
+            }
+            MessageBody::Image { base64, .. } => {
+                let padding = base64.bytes().rev().take_while(|&b| b == b'=').count();
+                let size = (base64.len() / 4 * 3).saturating_sub(padding);
+                check_field_len("image", size, IMAGE_MAX_BYTES)
+            }
+            MessageBody::News { articles } => {
+                if articles.is_empty() || articles.len() > NEWS_MAX_ARTICLES {
+                    return Err(WeComError::InvalidArticleCount {
+                        actual: articles.len(),
+                    });
+                }
+                for article in articles {
+                    article.validate()?;
+                    check_field_len("title", article.title.len(), ARTICLE_TITLE_MAX_BYTES)?;
+                    if let Some(desc) = &article.description {
+                        check_field_len("description", desc.len(), ARTICLE_DESC_MAX_BYTES)?;
+                    }
+                }
+                Ok(())
+            }
+            MessageBody::File { media_id } | MessageBody::Voice { media_id } => {
+                if media_id.trim().is_empty() {
+                    return Err(WeComError::EmptyMediaId);
+                }
+                Ok(())
+            }
+            MessageBody::Raw(_) => Ok(()),
+        }
+    }
+
+    /// Checks the content of a text or markdown message is within the given
+    /// limits, other messages always pass.
+    pub(crate) fn check_content_limits(
+        &self,
+        max_text_bytes: usize,
+        max_markdown_bytes: usize,
+    ) -> WeComResult<()> {
+        match &self.body {
+            MessageBody::Text { content, .. } => check_content_len(content, max_text_bytes),
+            MessageBody::Markdown { content } | MessageBody::MarkdownV2 { content } => {
+                check_content_len(content, max_markdown_bytes)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the JSON payload that is posted to the wecom webhook.
+    ///
+    /// Useful to hand the message over to another HTTP stack or a queue.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Returns the JSON payload as a `serde_json::Value`.
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("message is always serializable")
+    }
 }
 
-/// elements of wecom bot message type news.
-#[derive(Debug, Clone, Serialize)]
-pub struct Article<'a> {
-    /// No more than 128 bytes, it will be automatically truncated if exceeded.
-    pub title: Cow<'a, str>,
+impl<'a> From<&'a str> for Message<'a> {
+    /// Returns a text `Message` of the content, same as `Message::text`.
+    fn from(content: &'a str) -> Self {
+        Message::text(content)
+    }
+}
 
-    /// No more than 512 bytes, it will be automatically truncated if exceeded.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub description: Option<Cow<'a, str>>,
+impl From<String> for Message<'_> {
+    /// Returns a text `Message` of the content, same as `Message::text`.
+    fn from(content: String) -> Self {
+        Message::text(content)
+    }
+}
 
-    /// The link to be redirected after clicking.
-    pub url: Cow<'a, str>,
+/// Parses the shorthand of a message, e.g. read from a config file or a
+/// command line argument: `text:hello`, `markdown:# hi`, `markdown_v2:# hi`,
+/// `file:MEDIA_ID` or `voice:MEDIA_ID`.
+///
+/// A lowercase word directly followed by `:` and the content, e.g. a typo
+/// like `markdwon:# hi`, is rejected with `WeComError::UnknownMessagePrefix`.
+/// Any other string is a text message as a whole, so that plain text such as
+/// `error: disk full`, `note: 10:30` or an url needs no `text:` prefix.
+///
+/// ```
+/// # use wecom_bot::Message;
+///
+/// let msg: Message = "markdown:# Deploy done".parse().unwrap();
+/// assert_eq!(msg, Message::markdown("# Deploy done"));
+/// ```
+impl<'a> FromStr for Message<'a> {
+    type Err = WeComError;
 
-    /// The image link of the article message supports _JPG_ and _PNG_ formats.
-    /// The optimal size for better effect is 1068*455 for large images and
-    /// 150*150 for small images.
-    #[serde(rename = "picurl", skip_serializing_if = "Option::is_none")]
-    pub pic_url: Option<Cow<'a, str>>,
+    fn from_str(s: &str) -> WeComResult<Self> {
+        let (prefix, content) = match s.split_once(':') {
+            Some((prefix, content)) => (prefix, content.to_string()),
+            None => return Ok(Message::text(s.to_string())),
+        };
+        match prefix {
+            "text" => Ok(Message::text(content)),
+            "markdown" => Ok(Message::markdown(content)),
+            "markdown_v2" => Ok(Message::markdown_v2(content)),
+            "file" => Message::try_file(content),
+            "voice" if content.trim().is_empty() => Err(WeComError::EmptyMediaId),
+            "voice" => Ok(Message::voice(content)),
+            _ if is_prefix_like(prefix, &content) => {
+                Err(WeComError::UnknownMessagePrefix(prefix.to_string()))
+            }
+            _ => Ok(Message::text(s.to_string())),
+        }
+    }
 }
 
-impl<'a> Article<'a> {
-    /// Returns a article that can be clicked and displays with text and image
-    pub fn new<S>(title: S, url: S) -> Article<'a>
+/// Helpers of the wecom markdown extend syntax, which compose into the content
+/// of a markdown `Message`.
+pub mod markdown {
+    fn font<S>(color: &str, text: S) -> String
     where
-        S: Into<Cow<'a, str>>,
+        S: AsRef<str>,
     {
-        Self {
-            title: title.into(),
-            url: url.into(),
-            description: None,
-            pic_url: None,
-        }
+        format!(r#"<font color="{}">{}</font>"#, color, text.as_ref())
     }
 
-    /// Set the description on the article to show more detail about itself.
-    pub fn desc<D>(&mut self, desc: D) -> &mut Article<'a>
+    /// Returns the text wrapped in green font color.
+    ///
+    /// ```
+    /// assert_eq!(wecom_bot::markdown::info("ok"), r#"<font color="info">ok</font>"#);
+    /// ```
+    pub fn info<S>(text: S) -> String
     where
-        D: Into<Cow<'a, str>>,
+        S: AsRef<str>,
     {
-        self.description = Some(desc.into());
-        self
+        font("info", text)
     }
 
-    /// Set the image on the article card to make it more beautiful.
-    pub fn pic<P>(&mut self, pic: P) -> &mut Article<'a>
+    /// Returns the text wrapped in gray font color.
+    pub fn comment<S>(text: S) -> String
     where
-        P: Into<Cow<'a, str>>,
+        S: AsRef<str>,
     {
-        self.pic_url = Some(pic.into());
-        self
+        font("comment", text)
+    }
+
+    /// Returns the text wrapped in orange-red font color.
+    pub fn warning<S>(text: S) -> String
+    where
+        S: AsRef<str>,
+    {
+        font("warning", text)
     }
 }
 
-#[cfg(test)]
-mod message_tests {
-    use super::*;
+/// Assembles the content of a markdown `Message` piece by piece.
+///
+/// The content is kept as is without escaping, since markdown is raw text.
+///
+/// ```
+/// # use wecom_bot::MarkdownBuilder;
+///
+/// let msg = MarkdownBuilder::new()
+///     .line("# Deploy finished")
+///     .text("approved by ")
+///     .mention("zhangsan")
+///     .build();
+/// ```
+#[must_use = "builders do nothing unless `build` is called"]
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownBuilder {
+    content: String,
+}
 
-    #[test]
-    fn serialize_request() {
-        serialize_text();
-        serialize_markdown();
-        serialize_image();
-        serialize_article();
-        serialize_file();
+impl MarkdownBuilder {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    fn serialize_text() {
-        let text = Message::text("Text-Only");
-        assert_eq!(
-            "{\"msgtype\":\"text\",\"text\":{\"content\":\"Text-Only\"}}",
-            serde_json::to_string(&text).unwrap()
-        );
+    /// Appends the raw text to the content.
+    pub fn text<S>(mut self, text: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.content.push_str(text.as_ref());
+        self
+    }
 
-        let text = Message::text("Title").mentioned_list(vec!["", "uid2"]);
+    /// Appends the raw text followed by a line break to the content.
+    pub fn line<S>(mut self, line: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.content.push_str(line.as_ref());
+        self.content.push('\n');
+        self
+    }
+
+    /// Appends the `<@userid>` extend syntax to mention someone in the group.
+    pub fn mention<S>(mut self, userid: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.content.push_str("<@");
+        self.content.push_str(userid.as_ref());
+        self.content.push('>');
+        self
+    }
+
+    /// Returns the content assembled so far.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Returns a markdown `Message` with the assembled content.
+    pub fn build<'a>(self) -> Message<'a> {
+        Message::markdown(self.content)
+    }
+}
+
+/// Assembles a status report style markdown `Message` line by line, from
+/// headings, bullets, key-value rows and quotes.
+///
+/// ```
+/// # use wecom_bot::MarkdownSection;
+///
+/// let msg = MarkdownSection::new()
+///     .heading(2, "Nightly build")
+///     .kv("status", "passed")
+///     .kv("duration", "12m")
+///     .quote("triggered by the schedule")
+///     .build();
+/// ```
+#[must_use = "builders do nothing unless `build` is called"]
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownSection {
+    lines: Vec<String>,
+}
+
+impl MarkdownSection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a heading line, `level` is clamped to the 1 to 6 supported by
+    /// markdown.
+    pub fn heading<S>(mut self, level: usize, text: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let hashes = "#".repeat(level.clamp(1, 6));
+        self.lines.push(format!("{} {}", hashes, text.as_ref()));
+        self
+    }
+
+    /// Appends a bullet line.
+    pub fn bullet<S>(mut self, text: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.lines.push(format!("- {}", text.as_ref()));
+        self
+    }
+
+    /// Appends a key-value line, rendered as `**key**: value`.
+    pub fn kv<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.lines
+            .push(format!("**{}**: {}", key.as_ref(), value.as_ref()));
+        self
+    }
+
+    /// Appends a quote line.
+    pub fn quote<S>(mut self, text: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.lines.push(format!("> {}", text.as_ref()));
+        self
+    }
+
+    /// Returns the content assembled so far, one line per call.
+    pub fn content(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Returns a markdown `Message` with the assembled content.
+    pub fn build<'a>(self) -> Message<'a> {
+        Message::markdown(self.content())
+    }
+}
+
+/// elements of wecom bot message type news.
+#[must_use = "articles do nothing unless added to a news message"]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Article<'a> {
+    /// No more than 128 bytes, it will be automatically truncated if exceeded.
+    pub title: Cow<'a, str>,
+
+    /// No more than 512 bytes, it will be automatically truncated if exceeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<Cow<'a, str>>,
+
+    /// The link to be redirected after clicking.
+    pub url: Cow<'a, str>,
+
+    /// The image link of the article message supports _JPG_ and _PNG_ formats.
+    /// The optimal size for better effect is 1068*455 for large images and
+    /// 150*150 for small images.
+    #[serde(rename = "picurl", skip_serializing_if = "Option::is_none")]
+    pub pic_url: Option<Cow<'a, str>>,
+}
+
+impl<'a> Article<'a> {
+    /// Returns a article that can be clicked and displays with text and image
+    pub fn new<T, U>(title: T, url: U) -> Article<'a>
+    where
+        T: Into<Cow<'a, str>>,
+        U: Into<Cow<'a, str>>,
+    {
+        Self {
+            title: title.into(),
+            url: url.into(),
+            description: None,
+            pic_url: None,
+        }
+    }
+
+    /// Returns a article with all fields set at once, e.g. when generating
+    /// articles from rows of structured data.
+    ///
+    /// A bare `None` needs its type spelled out, e.g. `None::<&str>`.
+    ///
+    /// ```
+    /// # use wecom_bot::Article;
+    ///
+    /// let url = String::from("https://www.qq.com");
+    /// let article = Article::full("title", url, Some("description"), None::<&str>);
+    /// assert_eq!(article.pic_url, None);
+    /// ```
+    pub fn full<T, U, D, P>(
+        title: T,
+        url: U,
+        description: Option<D>,
+        pic_url: Option<P>,
+    ) -> Article<'a>
+    where
+        T: Into<Cow<'a, str>>,
+        U: Into<Cow<'a, str>>,
+        D: Into<Cow<'a, str>>,
+        P: Into<Cow<'a, str>>,
+    {
+        Self {
+            title: title.into(),
+            url: url.into(),
+            description: description.map(Into::into),
+            pic_url: pic_url.map(Into::into),
+        }
+    }
+
+    /// Set the description on the article to show more detail about itself.
+    pub fn desc<D>(&mut self, desc: D) -> &mut Article<'a>
+    where
+        D: Into<Cow<'a, str>>,
+    {
+        self.description = Some(desc.into());
+        self
+    }
+
+    /// Set the image on the article card to make it more beautiful.
+    pub fn pic<P>(&mut self, pic: P) -> &mut Article<'a>
+    where
+        P: Into<Cow<'a, str>>,
+    {
+        self.pic_url = Some(pic.into());
+        self
+    }
+
+    /// Same as `with_pic` but fetches the image first to check its aspect
+    /// ratio is close to the recommended 1068*455 of the large image, which is
+    /// shown for the first article of a news message.
+    ///
+    /// Returns `WeComError::PicAspectRatio` if the ratio is off by more than
+    /// 30%. The pic is set without the check if the image can not be fetched,
+    /// e.g. a 404 or a timeout, or its dimensions can not be determined.
+    #[cfg(feature = "client")]
+    pub fn with_validated_pic<P>(
+        self,
+        client: &reqwest::blocking::Client,
+        pic: P,
+    ) -> WeComResult<Article<'a>>
+    where
+        P: Into<Cow<'a, str>>,
+    {
+        let pic = pic.into();
+        let dimensions = Image::from_url(client, &pic)
+            .ok()
+            .and_then(|img| img.dimensions());
+        if let Some((width, height)) = dimensions {
+            let ratio = width as f64 / height.max(1) as f64;
+            let recommended = PIC_LARGE_WIDTH as f64 / PIC_LARGE_HEIGHT as f64;
+            if (ratio / recommended - 1.0).abs() > PIC_RATIO_TOLERANCE {
+                return Err(WeComError::PicAspectRatio { width, height });
+            }
+        }
+        Ok(self.with_pic(pic))
+    }
+
+    /// Checks the `url` and `pic_url` of the article are well-formed `http` or
+    /// `https` urls, an empty `url` is rejected as well.
+    ///
+    /// ```
+    /// # use wecom_bot::{Article, WeComError};
+    ///
+    /// assert!(Article::new("title", "https://www.qq.com").validate().is_ok());
+    /// assert!(matches!(
+    ///     Article::new("title", "ftp://www.qq.com").validate(),
+    ///     Err(WeComError::InvalidUrl { field: "url", .. })
+    /// ));
+    /// ```
+    pub fn validate(&self) -> WeComResult<()> {
+        check_http_url("url", &self.url)?;
+        if let Some(pic_url) = &self.pic_url {
+            check_http_url("pic_url", pic_url)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `desc` but takes and returns the article by value, so it can be
+    /// chained inline.
+    pub fn with_desc<D>(mut self, desc: D) -> Article<'a>
+    where
+        D: Into<Cow<'a, str>>,
+    {
+        self.description = Some(desc.into());
+        self
+    }
+
+    /// Same as `pic` but takes and returns the article by value, so it can be
+    /// chained inline.
+    pub fn with_pic<P>(mut self, pic: P) -> Article<'a>
+    where
+        P: Into<Cow<'a, str>>,
+    {
+        self.pic_url = Some(pic.into());
+        self
+    }
+}
+
+/// Whether `prefix:content` reads as a mistyped shorthand prefix rather than
+/// plain text, which has a space after the `:` or is an url.
+fn is_prefix_like(prefix: &str, content: &str) -> bool {
+    !prefix.is_empty()
+        && prefix
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+        && !content.starts_with(char::is_whitespace)
+        && !content.starts_with("//")
+}
+
+fn check_content_len(content: &str, limit: usize) -> WeComResult<()> {
+    if content.len() > limit {
+        return Err(WeComError::ContentTooLong {
+            limit,
+            actual: content.len(),
+        });
+    }
+    Ok(())
+}
+
+fn check_field_len(field: &'static str, actual: usize, limit: usize) -> WeComResult<()> {
+    if actual > limit {
+        return Err(WeComError::FieldTooLong {
+            field,
+            limit,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+fn check_http_url(field: &'static str, value: &str) -> WeComResult<()> {
+    match url::Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        _ => Err(WeComError::InvalidUrl {
+            field,
+            value: value.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod message_tests {
+    use super::*;
+
+    #[test]
+    fn serialize_request() {
+        serialize_text();
+        serialize_markdown();
+        serialize_markdown_v2();
+        serialize_image();
+        serialize_article();
+        serialize_file();
+        serialize_voice();
+    }
+
+    fn serialize_text() {
+        let text = Message::text("Text-Only");
+        assert_eq!(
+            "{\"msgtype\":\"text\",\"text\":{\"content\":\"Text-Only\"}}",
+            serde_json::to_string(&text).unwrap()
+        );
+
+        let text = Message::text("Title").mentioned_list(vec!["", "uid2"]);
         assert_eq!(
             "{\"msgtype\":\"text\",\"text\":{\"content\":\"Title\",\"mentioned_list\":[\"\",\"uid2\"]}}",
             serde_json::to_string(&text).unwrap()
@@ -322,6 +1232,222 @@ mod message_tests {
         );
     }
 
+    #[test]
+    fn text_lines() {
+        let msg = Message::text_lines(["deploy finished", "env: prod", "took: 42s"]).unwrap();
+        match &msg.body {
+            MessageBody::Text { content, .. } => {
+                assert_eq!(content, "deploy finished\nenv: prod\ntook: 42s");
+                assert_eq!(content.matches('\n').count(), 2);
+            }
+            _ => panic!("expected text message"),
+        }
+
+        let long = vec!["x".repeat(1024); 2];
+        match Message::text_lines(long) {
+            Err(WeComError::ContentTooLong { limit, actual }) => {
+                assert_eq!((limit, actual), (2048, 2049));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let long = vec!["x".repeat(1500); 2];
+        assert!(Message::text_lines(long.clone()).is_err());
+        assert!(Message::text_lines_with_limit(long, 4096).is_ok());
+    }
+
+    #[test]
+    fn text_chunks() {
+        let content = "中".repeat(1666) + "ab";
+        assert_eq!(content.len(), 5000);
+
+        let chunks = Message::text_chunks(&content);
+        assert_eq!(chunks.len(), 3);
+        let mut joined = String::new();
+        for chunk in &chunks {
+            match &chunk.body {
+                MessageBody::Text { content, .. } => {
+                    assert!(content.len() <= 2048);
+                    joined.push_str(content);
+                }
+                _ => panic!("expected text message"),
+            }
+        }
+        assert_eq!(joined, content);
+
+        let lines = format!("{}\n{}", "a".repeat(1500), "b".repeat(1500));
+        let chunks = Message::text_chunks(&lines);
+        assert_eq!(chunks.len(), 2);
+        assert!(
+            matches!(&chunks[0].body, MessageBody::Text { content, .. } if content.len() == 1500)
+        );
+
+        assert_eq!(Message::text_chunks("short").len(), 1);
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(
+            serde_json::to_string(&Message::text("hi")).unwrap(),
+            serde_json::to_string(&Message::from("hi")).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&Message::text("hi")).unwrap(),
+            serde_json::to_string(&Message::from(String::from("hi"))).unwrap()
+        );
+    }
+
+    #[test]
+    fn try_file() {
+        for media_id in ["", "  \t"] {
+            assert!(matches!(
+                Message::try_file(media_id),
+                Err(WeComError::EmptyMediaId)
+            ));
+        }
+        assert_eq!(
+            serde_json::to_string(&Message::try_file("3a8asd892asd8asd").unwrap()).unwrap(),
+            serde_json::to_string(&Message::file("3a8asd892asd8asd")).unwrap()
+        );
+    }
+
+    #[test]
+    fn mention_all() {
+        let text = Message::text("Everyone")
+            .mentioned_list(vec!["uid1"])
+            .mention_all();
+        assert_eq!(
+            r#"{"msgtype":"text","text":{"content":"Everyone","mentioned_list":["@all"]}}"#,
+            serde_json::to_string(&text).unwrap()
+        );
+
+        let md = Message::markdown("# Everyone").mention_all();
+        assert_eq!(
+            r##"{"msgtype":"markdown","markdown":{"content":"# Everyone"}}"##,
+            serde_json::to_string(&md).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        let messages = [
+            Message::text("hello")
+                .mentioned_list(["uid1"])
+                .mentioned_mobile_list(["1234567890"]),
+            Message::markdown("# hello"),
+            Message::markdown_v2("# hello"),
+            Message::image(Image::new(b"image".to_vec())),
+            Message::news(vec![
+                Article::new("title", "https://www.qq.com"),
+                Article::full(
+                    "title",
+                    "https://www.qq.com",
+                    Some("description"),
+                    Some("https://www.qq.com/pic.png"),
+                ),
+            ]),
+            Message::file("3a8asd892asd8asd"),
+            Message::voice("3a8asd892asd8asd"),
+        ];
+        for msg in messages {
+            let json = msg.to_json().unwrap();
+            assert_eq!(msg, serde_json::from_str::<Message>(&json).unwrap());
+        }
+
+        let mismatched = r#"{"msgtype":"markdown","text":{"content":"hello"}}"#;
+        assert!(serde_json::from_str::<Message>(mismatched).is_err());
+
+        let unknown = r#"{"msgtype":"template_card","template_card":{"card_type":"text_notice"}}"#;
+        let raw = serde_json::from_str::<Message>(unknown).unwrap();
+        assert_eq!(raw, Message::raw(serde_json::from_str(unknown).unwrap()));
+        assert_eq!(raw.to_json().unwrap(), unknown);
+    }
+
+    #[test]
+    fn parse_shorthand() {
+        let parse = |s: &str| s.parse::<Message>();
+
+        assert_eq!(parse("text:hello").unwrap(), Message::text("hello"));
+        assert_eq!(parse("hello").unwrap(), Message::text("hello"));
+        assert_eq!(
+            parse("Note: disk full").unwrap(),
+            Message::text("Note: disk full")
+        );
+        assert_eq!(parse("markdown:# hi").unwrap(), Message::markdown("# hi"));
+        assert_eq!(
+            parse("markdown_v2:# hi").unwrap(),
+            Message::markdown_v2("# hi")
+        );
+        assert_eq!(
+            parse("file:3a8asd892asd8asd").unwrap(),
+            Message::file("3a8asd892asd8asd")
+        );
+        assert_eq!(
+            parse("voice:3a8asd892asd8asd").unwrap(),
+            Message::voice("3a8asd892asd8asd")
+        );
+
+        assert!(matches!(parse("file:"), Err(WeComError::EmptyMediaId)));
+
+        for plain in [
+            "https://ci.example.com/job/1",
+            "error: disk full",
+            "disk: 90%",
+            "note: 10:30",
+        ] {
+            assert_eq!(parse(plain).unwrap(), Message::text(plain));
+        }
+
+        for (shorthand, unknown) in [
+            ("video:3a8asd892asd8asd", "video"),
+            ("markdwon:# hi", "markdwon"),
+        ] {
+            match parse(shorthand) {
+                Err(WeComError::UnknownMessagePrefix(prefix)) => assert_eq!(prefix, unknown),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn dedup_mentions() {
+        let text = Message::text("Dedup").mentioned_list(["@all", "@all", "uid1"]);
+        assert_eq!(
+            r#"{"msgtype":"text","text":{"content":"Dedup","mentioned_list":["@all","uid1"]}}"#,
+            text.to_json().unwrap()
+        );
+
+        let text = Message::text("Dedup")
+            .mentioned_mobile_list(["@all", "1234567890", "1234567890"])
+            .add_mentioned("uid1")
+            .add_mentioned("@all")
+            .add_mentioned("uid1");
+        assert_eq!(
+            r#"{"msgtype":"text","text":{"content":"Dedup","mentioned_list":["uid1","@all"],"mentioned_mobile_list":["1234567890"]}}"#,
+            text.to_json().unwrap()
+        );
+    }
+
+    #[test]
+    fn add_mentioned() {
+        let text = Message::text("Append")
+            .add_mentioned("uid1")
+            .add_mentioned(String::from("uid2"))
+            .add_mentioned_mobile("1234567890");
+        assert_eq!(
+            r#"{"msgtype":"text","text":{"content":"Append","mentioned_list":["uid1","uid2"],"mentioned_mobile_list":["1234567890"]}}"#,
+            serde_json::to_string(&text).unwrap()
+        );
+
+        let text = Message::text("Append")
+            .mentioned_list(vec!["uid1"])
+            .add_mentioned("uid2");
+        assert_eq!(
+            r#"{"msgtype":"text","text":{"content":"Append","mentioned_list":["uid1","uid2"]}}"#,
+            serde_json::to_string(&text).unwrap()
+        );
+    }
+
     fn serialize_markdown() {
         let md = Message::markdown(r"# Markdown");
         assert_eq!(
@@ -330,12 +1456,30 @@ mod message_tests {
         );
     }
 
+    fn serialize_markdown_v2() {
+        let md = Message::markdown_v2("| a | b |\n| - | - |\n| 1 | 2 |");
+        assert_eq!(
+            r#"{"msgtype":"markdown_v2","markdown_v2":{"content":"| a | b |\n| - | - |\n| 1 | 2 |"}}"#,
+            serde_json::to_string(&md).unwrap()
+        );
+    }
+
     fn serialize_image() {
         let img = Message::image(Image::new(b"image".to_vec()));
         assert_eq!(
             "{\"msgtype\":\"image\",\"image\":{\"base64\":\"aW1hZ2U=\",\"md5\":\"78805a221a988e79ef3f42d7c5bfd418\"}}",
             serde_json::to_string(&img).unwrap()
         );
+        assert_eq!(
+            serde_json::to_string(&img).unwrap(),
+            serde_json::to_string(&Message::image_from_bytes(b"image".to_vec())).unwrap()
+        );
+
+        let from_file = Message::image_from_file("src/tests/imgs/tiny-rust-logo.png").unwrap();
+        assert!(serde_json::to_string(&from_file)
+            .unwrap()
+            .contains("4d1b24690a324e7ac911c3c721982951"));
+        assert!(Message::image_from_file("src/tests/imgs/missing.png").is_err());
     }
 
     fn serialize_article() {
@@ -348,6 +1492,290 @@ mod message_tests {
             r#"{"msgtype":"news","news":{"articles":[{"title":"","url":""},{"title":"中秋节礼品领取","description":"今年中秋节公司有豪礼相送","url":"www.qq.com","picurl":"http://res.mail.qq.com/node/ww/wwopenmng/images/independent/doc/test_pic_msg1.png"}]}}"#,
             serde_json::to_string(&news).unwrap()
         );
+        assert_eq!(
+            serde_json::to_string(&news).unwrap(),
+            news.to_json().unwrap()
+        );
+        assert_eq!(news.to_value()["news"]["articles"][1]["url"], "www.qq.com");
+    }
+
+    #[test]
+    fn article_builder() {
+        let news = Message::news(vec![Article::new("中秋节礼品领取", "www.qq.com")
+            .with_desc("今年中秋节公司有豪礼相送")
+            .with_pic(
+                "http://res.mail.qq.com/node/ww/wwopenmng/images/independent/doc/test_pic_msg1.png",
+            )]);
+        assert_eq!(
+            r#"{"msgtype":"news","news":{"articles":[{"title":"中秋节礼品领取","description":"今年中秋节公司有豪礼相送","url":"www.qq.com","picurl":"http://res.mail.qq.com/node/ww/wwopenmng/images/independent/doc/test_pic_msg1.png"}]}}"#,
+            serde_json::to_string(&news).unwrap()
+        );
+    }
+
+    #[test]
+    fn article_mixed_types() {
+        let news = Message::news(vec![Article::new("lit", format!("{}", 1))]);
+        assert_eq!(
+            r#"{"msgtype":"news","news":{"articles":[{"title":"lit","url":"1"}]}}"#,
+            news.to_json().unwrap()
+        );
+    }
+
+    #[test]
+    fn article_full() {
+        let news = Message::news(vec![Article::full(
+            "中秋节礼品领取",
+            "www.qq.com",
+            Some("今年中秋节公司有豪礼相送"),
+            Some(
+                "http://res.mail.qq.com/node/ww/wwopenmng/images/independent/doc/test_pic_msg1.png",
+            ),
+        )]);
+        assert_eq!(
+            r#"{"msgtype":"news","news":{"articles":[{"title":"中秋节礼品领取","description":"今年中秋节公司有豪礼相送","url":"www.qq.com","picurl":"http://res.mail.qq.com/node/ww/wwopenmng/images/independent/doc/test_pic_msg1.png"}]}}"#,
+            serde_json::to_string(&news).unwrap()
+        );
+
+        let url = String::from("www.qq.com");
+        let mixed = Article::full("title", url, Some(String::from("desc")), None::<&str>);
+        assert_eq!(
+            r#"{"title":"title","description":"desc","url":"www.qq.com"}"#,
+            serde_json::to_string(&mixed).unwrap()
+        );
+    }
+
+    #[test]
+    fn try_news() {
+        let articles = |n: usize| vec![Article::new("title", "https://www.qq.com"); n];
+
+        for n in [0, 9] {
+            match Message::try_news(articles(n)) {
+                Err(WeComError::InvalidArticleCount { actual }) => assert_eq!(actual, n),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        for n in [1, 8] {
+            assert!(Message::try_news(articles(n)).is_ok());
+        }
+    }
+
+    #[test]
+    fn validate_article_url() {
+        assert!(Article::new("title", "https://www.qq.com")
+            .with_pic("http://res.mail.qq.com/test_pic_msg1.png")
+            .validate()
+            .is_ok());
+
+        for (article, field) in [
+            (Article::new("title", "ftp://www.qq.com"), "url"),
+            (Article::new("title", "not a url"), "url"),
+            (Article::new("title", ""), "url"),
+            (
+                Article::new("title", "https://www.qq.com").with_pic("www.qq.com/a.png"),
+                "pic_url",
+            ),
+        ] {
+            match Message::try_news(vec![article]) {
+                Err(WeComError::InvalidUrl { field: f, .. }) => assert_eq!(f, field),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn markdown_mention() {
+        let msg = Message::markdown_mention("# Deploy failed", ["zhangsan", "lisi"]);
+        assert_eq!(
+            r##"{"msgtype":"markdown","markdown":{"content":"# Deploy failed\n<@zhangsan> <@lisi>"}}"##,
+            serde_json::to_string(&msg).unwrap()
+        );
+
+        let msg = Message::markdown_mention("# Deploy failed", Vec::<&str>::new());
+        assert_eq!(
+            r##"{"msgtype":"markdown","markdown":{"content":"# Deploy failed"}}"##,
+            serde_json::to_string(&msg).unwrap()
+        );
+    }
+
+    #[test]
+    fn validate_markdown() {
+        assert!(Message::validate_markdown("# Deploy finished\n> env: prod").is_ok());
+        assert!(Message::validate_markdown("[link](https://www.qq.com) | done").is_ok());
+
+        for (content, construct) in [
+            ("# Logo\n![logo](https://www.qq.com/logo.png)", "image"),
+            ("| env | status |\n| --- | :---: |\n| prod | ok |", "table"),
+        ] {
+            match Message::validate_markdown(content) {
+                Err(WeComError::UnsupportedMarkdown(c)) => assert_eq!(c, construct),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "client")]
+    fn with_validated_pic() {
+        use crate::tests::{MockServer, Reply};
+
+        let png = |width: u32, height: u32| {
+            let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+            data.extend_from_slice(&width.to_be_bytes());
+            data.extend_from_slice(&height.to_be_bytes());
+            data
+        };
+        let server = MockServer::start(vec![
+            Reply::bytes(200, "image/png", png(1068, 455)),
+            Reply::bytes(200, "image/png", png(150, 150)),
+            Reply::bytes(200, "image/png", b"not a png".to_vec()),
+            Reply::bytes(404, "text/html", b"not found".to_vec()),
+        ]);
+        let client = reqwest::blocking::Client::new();
+        let article = || Article::new("title", "https://www.qq.com");
+
+        let wide = format!("{}/wide.png", server.url());
+        let validated = article().with_validated_pic(&client, wide.clone()).unwrap();
+        assert_eq!(validated.pic_url.as_deref(), Some(wide.as_str()));
+
+        let square = format!("{}/square.png", server.url());
+        match article().with_validated_pic(&client, square) {
+            Err(WeComError::PicAspectRatio { width, height }) => {
+                assert_eq!((width, height), (150, 150))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let unknown = format!("{}/unknown.png", server.url());
+        assert!(article().with_validated_pic(&client, unknown).is_ok());
+
+        let missing = format!("{}/missing.png", server.url());
+        let unchecked = article()
+            .with_validated_pic(&client, missing.clone())
+            .unwrap();
+        assert_eq!(unchecked.pic_url.as_deref(), Some(missing.as_str()));
+    }
+
+    #[test]
+    fn validate() {
+        let valid = [
+            Message::text("hello"),
+            Message::markdown("# hello"),
+            Message::markdown_v2("# hello"),
+            Message::image(Image::new(b"image".to_vec())),
+            Message::news(vec![Article::new("title", "https://www.qq.com")]),
+            Message::file("3a8asd892asd8asd"),
+            Message::voice("3a8asd892asd8asd"),
+        ];
+        for msg in &valid {
+            assert!(msg.validate().is_ok(), "{:?}", msg);
+        }
+
+        assert!(matches!(
+            Message::text("x".repeat(2049)).validate(),
+            Err(WeComError::ContentTooLong { limit: 2048, .. })
+        ));
+        assert!(matches!(
+            Message::markdown("x".repeat(4097)).validate(),
+            Err(WeComError::ContentTooLong { limit: 4096, .. })
+        ));
+        assert!(matches!(
+            Message::markdown_v2("x".repeat(4097)).validate(),
+            Err(WeComError::ContentTooLong { limit: 4096, .. })
+        ));
+        match Message::image(Image::new(vec![0; 2 * 1024 * 1024 + 1])).validate() {
+            Err(WeComError::FieldTooLong { field, actual, .. }) => {
+                assert_eq!((field, actual), ("image", 2 * 1024 * 1024 + 1))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            Message::news(vec![]).validate(),
+            Err(WeComError::InvalidArticleCount { actual: 0 })
+        ));
+        assert!(matches!(
+            Message::news(vec![Article::new("title", "url")]).validate(),
+            Err(WeComError::InvalidUrl { field: "url", .. })
+        ));
+        let long_title = "x".repeat(129);
+        assert!(matches!(
+            Message::news(vec![Article::new(
+                long_title.as_str(),
+                "https://www.qq.com"
+            )])
+            .validate(),
+            Err(WeComError::FieldTooLong { field: "title", .. })
+        ));
+        assert!(matches!(
+            Message::news(vec![
+                Article::new("title", "https://www.qq.com").with_desc("x".repeat(513))
+            ])
+            .validate(),
+            Err(WeComError::FieldTooLong {
+                field: "description",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Message::file(" ").validate(),
+            Err(WeComError::EmptyMediaId)
+        ));
+        assert!(matches!(
+            Message::voice("").validate(),
+            Err(WeComError::EmptyMediaId)
+        ));
+    }
+
+    #[test]
+    fn markdown_builder() {
+        let builder = MarkdownBuilder::new()
+            .line("# Deploy finished")
+            .text("approved by ")
+            .mention("zhangsan")
+            .text(", thanks!");
+        assert_eq!(
+            builder.content(),
+            "# Deploy finished\napproved by <@zhangsan>, thanks!"
+        );
+
+        assert_eq!(
+            r##"{"msgtype":"markdown","markdown":{"content":"# Deploy finished\napproved by <@zhangsan>, thanks!"}}"##,
+            serde_json::to_string(&builder.build()).unwrap()
+        );
+    }
+
+    #[test]
+    fn markdown_section() {
+        let section = MarkdownSection::new()
+            .heading(2, "Nightly build")
+            .kv("status", "passed")
+            .kv("duration", "12m");
+        assert_eq!(
+            section.content(),
+            "## Nightly build\n**status**: passed\n**duration**: 12m"
+        );
+        assert_eq!(
+            section.clone().build(),
+            Message::markdown("## Nightly build\n**status**: passed\n**duration**: 12m")
+        );
+
+        let section = MarkdownSection::new()
+            .heading(9, "clamped")
+            .bullet("item")
+            .quote("quoted");
+        assert_eq!(section.content(), "###### clamped\n- item\n> quoted");
+    }
+
+    #[test]
+    fn markdown_font_color() {
+        assert_eq!(markdown::info("ok"), r#"<font color="info">ok</font>"#);
+        assert_eq!(
+            markdown::comment(String::from("note")),
+            r#"<font color="comment">note</font>"#
+        );
+        assert_eq!(
+            markdown::warning("fail"),
+            r#"<font color="warning">fail</font>"#
+        );
     }
 
     fn serialize_file() {
@@ -358,4 +1786,13 @@ mod message_tests {
             serde_json::to_string(&file).unwrap(),
         );
     }
+
+    fn serialize_voice() {
+        let voice = Message::voice("3a8asd892asd8asd");
+
+        assert_eq!(
+            r#"{"msgtype":"voice","voice":{"media_id":"3a8asd892asd8asd"}}"#,
+            serde_json::to_string(&voice).unwrap(),
+        );
+    }
 }
@@ -1,6 +1,10 @@
 use serde::Deserialize;
 
-#[derive(Debug, Default, Deserialize)]
+use crate::error::WeComError;
+use crate::media::MediaType;
+use crate::message::Message;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
 pub struct SendResp {
     #[serde(rename = "errcode")]
     pub err_code: i64,
@@ -9,7 +13,44 @@ pub struct SendResp {
     pub err_msg: String,
 }
 
+impl SendResp {
+    pub fn is_ok(&self) -> bool {
+        self.err_code.eq(&0)
+    }
+
+    /// Returns the `errcode` and `errmsg` if the message was rejected.
+    pub fn err(&self) -> Option<(i64, &str)> {
+        if self.is_ok() {
+            None
+        } else {
+            Some((self.err_code, &self.err_msg))
+        }
+    }
+
+    /// Returns the request id following `rid:` in `errmsg`, which is asked by
+    /// wecom support to look into a failed request, or `None` if absent.
+    pub fn request_id(&self) -> Option<&str> {
+        let (_, rest) = self.err_msg.split_once("rid:")?;
+        let rid = rest
+            .trim_start()
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .next()?;
+        (!rid.is_empty()).then_some(rid)
+    }
+}
+
+/// Error body returned by the wecom bot API, used as a fallback when the
+/// response could not be parsed into the expected type.
 #[derive(Debug, Deserialize)]
+pub(crate) struct ApiError {
+    #[serde(rename = "errcode")]
+    pub err_code: i64,
+
+    #[serde(rename = "errmsg", default)]
+    pub err_msg: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct UploadResp {
     #[serde(rename = "errcode")]
     pub err_code: i64,
@@ -32,7 +73,7 @@ impl Default for UploadResp {
         UploadResp {
             err_code: 0,
             err_msg: String::from("success"),
-            media_type: crate::MediaType::File.to_string(),
+            media_type: MediaType::File.to_string(),
             media_id: String::new(),
             created_at: String::new(),
         }
@@ -47,4 +88,142 @@ impl UploadResp {
     pub fn is_ok(&self) -> bool {
         self.err_code.eq(&0)
     }
+
+    /// Returns `media_type` parsed as `MediaType`, or `None` if it is a type
+    /// unknown to this crate.
+    pub fn media_type_enum(&self) -> Option<MediaType> {
+        self.media_type.parse().ok()
+    }
+
+    /// Returns `created_at` as unix timestamp in seconds, or `None` if it is
+    /// empty or not a number.
+    pub fn created_at_unix(&self) -> Option<i64> {
+        self.created_at.trim().parse().ok()
+    }
+
+    /// Returns `created_at` as UTC datetime, or `None` if it is empty or not a
+    /// valid timestamp.
+    #[cfg(feature = "chrono")]
+    pub fn created_at_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.created_at_unix()?, 0)
+    }
+}
+
+/// Returns a file `Message` of the uploaded `media_id`, to send the file
+/// right after uploading it.
+///
+/// Returns `WeComError::Api` if the upload was rejected, or
+/// `WeComError::EmptyMediaId` if no `media_id` was returned.
+impl<'a> TryFrom<&'a UploadResp> for Message<'a> {
+    type Error = WeComError;
+
+    fn try_from(resp: &'a UploadResp) -> Result<Self, Self::Error> {
+        if !resp.is_ok() {
+            return Err(WeComError::Api {
+                err_code: resp.err_code,
+                err_msg: resp.err_msg.clone(),
+            });
+        }
+        Message::try_file(resp.media_id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod response_test {
+    use super::{SendResp, UploadResp};
+    use crate::error::WeComError;
+    use crate::media::MediaType;
+    use crate::message::Message;
+
+    #[test]
+    fn send_is_ok() {
+        let resp: SendResp = serde_json::from_str(r#"{"errcode":0,"errmsg":"ok"}"#).unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(resp.err(), None);
+
+        let resp: SendResp =
+            serde_json::from_str(r#"{"errcode":93000,"errmsg":"invalid webhook url"}"#).unwrap();
+        assert!(!resp.is_ok());
+        assert_eq!(resp.err(), Some((93000, "invalid webhook url")));
+    }
+
+    #[test]
+    fn send_request_id() {
+        let resp = SendResp {
+            err_code: 60020,
+            err_msg: String::from(
+                "not allow to access from your ip, hint: [1700000000_52_6b4f], from ip: 10.0.0.1, rid: 6556f1a2-1b2c3d4e, more info at https://open.work.weixin.qq.com/devtool/query?e=60020",
+            ),
+        };
+        assert_eq!(resp.request_id(), Some("6556f1a2-1b2c3d4e"));
+
+        let resp = SendResp {
+            err_code: 0,
+            err_msg: String::from("ok"),
+        };
+        assert_eq!(resp.request_id(), None);
+    }
+
+    #[test]
+    fn created_at() {
+        let mut resp = UploadResp {
+            created_at: String::from("1700000000"),
+            ..Default::default()
+        };
+        assert_eq!(resp.created_at_unix(), Some(1700000000));
+        #[cfg(feature = "chrono")]
+        assert_eq!(
+            resp.created_at_datetime().unwrap().to_rfc3339(),
+            "2023-11-14T22:13:20+00:00"
+        );
+
+        resp.created_at = String::new();
+        assert_eq!(resp.created_at_unix(), None);
+        resp.created_at = String::from("yesterday");
+        assert_eq!(resp.created_at_unix(), None);
+    }
+
+    #[test]
+    fn media_type_enum() {
+        let mut resp = UploadResp {
+            media_type: String::from("voice"),
+            ..Default::default()
+        };
+        assert!(matches!(resp.media_type_enum(), Some(MediaType::Voice)));
+        resp.media_type = String::from("sticker");
+        assert!(resp.media_type_enum().is_none());
+
+        resp.media_type = String::from("file");
+        assert_eq!(resp.media_type_enum(), Some(MediaType::File));
+        assert_eq!(resp.clone(), resp);
+    }
+
+    #[test]
+    fn upload_into_file_message() {
+        let resp: UploadResp = serde_json::from_str(
+            r#"{"errcode":0,"errmsg":"ok","type":"file","media_id":"1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0","created_at":"1380000000"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Message::try_from(&resp).unwrap(),
+            Message::file("1G6nrLmr5EC3MMb_-zK1dDdzmd0p7cNliYu9V5w7o8K0")
+        );
+
+        let rejected = UploadResp {
+            err_code: 93000,
+            err_msg: String::from("invalid webhook url"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Message::try_from(&rejected),
+            Err(WeComError::Api {
+                err_code: 93000,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Message::try_from(&UploadResp::new()),
+            Err(WeComError::EmptyMediaId)
+        ));
+    }
 }
@@ -0,0 +1,236 @@
+//! A minimal HTTP server for exercising the bot clients without reaching the
+//! real wecom bot API.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A request received by the `MockServer`.
+#[derive(Debug, Clone)]
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the value of the first header named `name`, case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn body_str(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// The canned reply for a single connection.
+#[derive(Debug, Clone)]
+pub(crate) enum Reply {
+    Response {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    },
+    /// Reads the request then closes the connection without responding.
+    Drop,
+    /// Waits for the duration before replying.
+    Delayed(Duration, Box<Reply>),
+}
+
+impl Reply {
+    pub fn json(status: u16, body: &str) -> Self {
+        Reply::Response {
+            status,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn bytes(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        Reply::Response {
+            status,
+            headers: vec![("Content-Type".into(), content_type.into())],
+            body,
+        }
+    }
+
+    pub fn delay(self, delay: Duration) -> Self {
+        Reply::Delayed(delay, Box::new(self))
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let Reply::Response { headers, .. } = &mut self {
+            headers.push((name.into(), value.into()));
+        }
+        self
+    }
+}
+
+/// Serves the given replies in order, one per accepted connection, repeating
+/// the last reply once exhausted. Every received request is recorded.
+pub(crate) struct MockServer {
+    addr: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+    in_flight: Arc<InFlight>,
+}
+
+/// Counts the requests being handled and the peak of them.
+#[derive(Default)]
+struct InFlight {
+    current: AtomicUsize,
+    max: AtomicUsize,
+}
+
+impl MockServer {
+    pub fn start(replies: Vec<Reply>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let in_flight = Arc::new(InFlight::default());
+
+        let recorded = requests.clone();
+        let counter = in_flight.clone();
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(stream) = stream else { break };
+                let reply = replies
+                    .get(i)
+                    .or_else(|| replies.last())
+                    .cloned()
+                    .unwrap_or_else(|| Reply::json(200, r#"{"errcode":0,"errmsg":"ok"}"#));
+                let recorded = recorded.clone();
+                let counter = counter.clone();
+                thread::spawn(move || handle(stream, reply, recorded, counter));
+            }
+        });
+
+        MockServer {
+            addr,
+            requests,
+            in_flight,
+        }
+    }
+
+    /// Returns the base url of the server, e.g. `http://127.0.0.1:12345`.
+    pub fn url(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the maximum number of requests that were handled at once.
+    pub fn max_in_flight(&self) -> usize {
+        self.in_flight.max.load(Ordering::SeqCst)
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    reply: Reply,
+    recorded: Arc<Mutex<Vec<Request>>>,
+    in_flight: Arc<InFlight>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let Some(req) = read_request(&mut reader) else {
+        return;
+    };
+    recorded.lock().unwrap().push(req);
+
+    let current = in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+    in_flight.max.fetch_max(current, Ordering::SeqCst);
+    respond(stream, reply);
+    in_flight.current.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn respond(stream: TcpStream, reply: Reply) {
+    let mut reply = reply;
+    while let Reply::Delayed(delay, inner) = reply {
+        thread::sleep(delay);
+        reply = *inner;
+    }
+
+    let (status, headers, body) = match reply {
+        Reply::Drop | Reply::Delayed(..) => return,
+        Reply::Response {
+            status,
+            headers,
+            body,
+        } => (status, headers, body),
+    };
+
+    let mut out = stream;
+    let mut head = format!("HTTP/1.1 {} MOCK\r\n", status);
+    for (k, v) in headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    let _ = out.write_all(head.as_bytes());
+    let _ = out.write_all(&body);
+    let _ = out.flush();
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+
+    let find = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _): &&(String, String)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    };
+
+    let mut body = Vec::new();
+    if let Some(len) = find("content-length") {
+        body.resize(len.parse().ok()?, 0);
+        reader.read_exact(&mut body).ok()?;
+    } else if find("transfer-encoding").is_some_and(|v| v.contains("chunked")) {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
@@ -0,0 +1,186 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::bot::WeComBot;
+use crate::error::{WeComError, WeComResult};
+use crate::message::Message;
+use crate::response::SendResp;
+
+/// The wait before a rate limited message is sent again if the response has
+/// no `Retry-After` header, wecom allows 20 messages per minute.
+const RATE_LIMITED_WAIT: Duration = Duration::from_secs(3);
+
+/// Sends messages through a `WeComBot` owned by a background thread, so that
+/// the caller does not block on the network, e.g. for fire-and-forget
+/// alerting.
+///
+/// Queued messages are sent one by one in order. A message failed with
+/// `WeComError::RateLimited` is sent again after the `retry_after` wait, or 3
+/// seconds without one, before the next one is sent. Dropping the worker, or
+/// calling `shutdown`, waits for the queue to be drained.
+///
+/// ```
+/// # use wecom_bot::{Message, WeComBot, WeComBotWorker, WeComError};
+/// # fn main() -> Result<(), WeComError> {
+/// let bot = WeComBot::builder()
+///     .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+///     .dry_run(true)
+///     .build()?;
+/// let worker = WeComBotWorker::spawn_with(bot, |sent| {
+///     if let Err(e) = sent {
+///         eprintln!("alert not sent: {}", e);
+///     }
+/// });
+/// worker.enqueue(Message::text("disk usage above 90%")).unwrap();
+/// worker.shutdown();
+/// # Ok(())
+/// # }
+/// ```
+pub struct WeComBotWorker {
+    sender: Option<mpsc::Sender<Message<'static>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl WeComBotWorker {
+    /// Spawns the worker thread sending with `bot`, the result of each
+    /// message is dropped.
+    pub fn spawn(bot: WeComBot) -> WeComBotWorker {
+        Self::spawn_with(bot, |_| {})
+    }
+
+    /// Same as `spawn` but calls `on_sent` on the worker thread with the
+    /// result of each message, e.g. to report the failures.
+    pub fn spawn_with<F>(bot: WeComBot, mut on_sent: F) -> WeComBotWorker
+    where
+        F: FnMut(WeComResult<SendResp>) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Message<'static>>();
+        let handle = thread::spawn(move || {
+            for msg in receiver {
+                on_sent(send_waiting(&bot, msg));
+            }
+        });
+        WeComBotWorker {
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Queues the message to be sent and returns immediately.
+    ///
+    /// The message is handed back if the worker thread is gone, which only
+    /// happens if `on_sent` panicked.
+    pub fn enqueue(&self, msg: Message<'static>) -> Result<(), Message<'static>> {
+        match &self.sender {
+            Some(sender) => sender.send(msg).map_err(|e| e.0),
+            None => Err(msg),
+        }
+    }
+
+    /// Stops accepting messages and waits for the queued ones to be sent.
+    pub fn shutdown(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        drop(self.sender.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Sends the message, waiting out the rate limit of wecom until it is sent or
+/// fails with another error.
+fn send_waiting(bot: &WeComBot, msg: Message<'static>) -> WeComResult<SendResp> {
+    loop {
+        match bot.send(msg.clone()) {
+            Err(WeComError::RateLimited { retry_after }) => {
+                thread::sleep(retry_after.unwrap_or(RATE_LIMITED_WAIT));
+            }
+            sent => return sent,
+        }
+    }
+}
+
+impl Drop for WeComBotWorker {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[cfg(test)]
+mod worker_test {
+    use std::sync::{Arc, Mutex};
+
+    use super::WeComBotWorker;
+    use crate::bot::WeComBot;
+    use crate::message::Message;
+    use crate::tests::{MockServer, Reply};
+
+    const SEND_OK: &str = r#"{"errcode":0,"errmsg":"ok"}"#;
+
+    #[test]
+    fn enqueue_in_order() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(500, ""),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&results);
+        let worker = WeComBotWorker::spawn_with(bot, move |sent| {
+            recorded.lock().unwrap().push(sent.is_ok())
+        });
+        for content in ["first", "second", "third"] {
+            worker.enqueue(Message::text(content)).unwrap();
+        }
+        worker.shutdown();
+
+        assert_eq!(*results.lock().unwrap(), vec![true, false, true]);
+        let bodies: Vec<String> = server.requests().iter().map(|r| r.body_str()).collect();
+        assert!(bodies[0].contains("first"));
+        assert!(bodies[1].contains("second"));
+        assert!(bodies[2].contains("third"));
+    }
+
+    #[test]
+    fn enqueue_rate_limited() {
+        let server = MockServer::start(vec![
+            Reply::json(200, SEND_OK),
+            Reply::json(200, r#"{"errcode":45009,"errmsg":"api freq out of limit"}"#)
+                .header("Retry-After", "0"),
+            Reply::json(200, SEND_OK),
+            Reply::json(200, SEND_OK),
+        ]);
+        let bot = WeComBot::builder()
+            .key("693a91f6-7xxx-4bc4-97a0-0ec2sifa5aaa")
+            .base_url(server.url())
+            .build()
+            .unwrap();
+
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&results);
+        let worker = WeComBotWorker::spawn_with(bot, move |sent| {
+            recorded.lock().unwrap().push(sent.is_ok())
+        });
+        for content in ["first", "second", "third"] {
+            worker.enqueue(Message::text(content)).unwrap();
+        }
+        worker.shutdown();
+
+        assert_eq!(*results.lock().unwrap(), vec![true, true, true]);
+        let bodies: Vec<String> = server.requests().iter().map(|r| r.body_str()).collect();
+        assert_eq!(bodies.len(), 4);
+        assert!(bodies[1].contains("second"));
+        assert!(bodies[2].contains("second"));
+        assert!(bodies[3].contains("third"));
+    }
+}